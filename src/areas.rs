@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::InertiaError;
+use crate::tasks::{Task, Tasks};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Area {
+    pub name: String,                // The unique name of the area
+    pub description: Option<String>, // What the area is for
+    #[serde(default)]
+    pub archived: bool, // Archived areas and their tasks are hidden
+    pub tags: Option<Vec<String>>,   // Tags given to new tasks in the area
+}
+
+impl Area {
+    pub fn new(name: String, description: Option<String>, tags: Option<Vec<String>>) -> Self {
+        Self {
+            name,
+            description,
+            archived: false,
+            tags,
+        }
+    }
+}
+
+impl Tasks {
+    /// Returns an area from its name
+    pub fn area(&self, name: &str) -> Result<&Area, InertiaError> {
+        self.areas
+            .iter()
+            .find(|area| area.name == name)
+            .ok_or_else(|| InertiaError::no_area(name))
+    }
+
+    /// Returns an area from its name, to change it
+    pub fn area_mut(&mut self, name: &str) -> Result<&mut Area, InertiaError> {
+        self.areas
+            .iter_mut()
+            .find(|area| area.name == name)
+            .ok_or_else(|| InertiaError::no_area(name))
+    }
+
+    pub fn push_area(&mut self, area: Area) -> Result<(), InertiaError> {
+        if self.area(&area.name).is_ok() {
+            Err(InertiaError::area_exists(&area.name))
+        } else {
+            self.areas.push(area);
+            Ok(())
+        }
+    }
+
+    /// Renames an area, moving its tasks along with it
+    pub fn rename_area(&mut self, name: &str, new_name: &str) -> Result<(), InertiaError> {
+        if self.area(new_name).is_ok() {
+            return Err(InertiaError::area_exists(new_name));
+        }
+        self.area_mut(name)?.name = new_name.to_string();
+
+        for task in self.tasks.iter_mut() {
+            if task.area.as_deref() == Some(name) {
+                task.area = Some(new_name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if a task belongs to an area that has been archived
+    pub fn in_archived_area(&self, task: &Task) -> bool {
+        match &task.area {
+            Some(name) => self.area(name).map(|area| area.archived).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Returns the tasks in an area
+    pub fn area_tasks(&self, name: &str) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| task.area.as_deref() == Some(name))
+            .collect()
+    }
+}
@@ -1,10 +1,53 @@
-use clap::{Args, Parser, Subcommand};
+use clap::builder::BoolishValueParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 pub struct TasksArgs {
     #[clap(subcommand)]
     pub command: Commands,
+
+    /// Directory of the tasks repository, instead of the one in $XDG_DATA_HOME
+    #[arg(long, global = true, env = "INERTIA_DIR", value_name = "DIR")]
+    pub repo: Option<PathBuf>,
+
+    /// Named profile to use, such as work or personal
+    #[arg(short, long, global = true, env = "INERTIA_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Use your own tasks, even inside a project with its own tasks
+    #[arg(long, global = true)]
+    pub global: bool,
+
+    /// Stage changes without committing them, to commit later in one batch
+    #[arg(long, global = true, env = "INERTIA_NO_COMMIT", value_parser = BoolishValueParser::new())]
+    pub no_commit: bool,
+
+    /// Change many tasks at once without asking first
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// How to print tasks, as tables or JSON for scripts
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_enum,
+        default_value = "table",
+        env = "INERTIA_OUTPUT"
+    )]
+    pub output: OutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    /// Coloured tables
+    Table,
+    /// A JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
 }
 
 #[derive(Subcommand, PartialEq, Eq, Debug)]
@@ -15,8 +58,10 @@ pub enum Commands {
     Del(DeleteTask),
     /// Deletes all tasks
     Clear,
-    /// Shows info about a task
+    /// Shows info about a task, or the tasks matching a filter
     Show(ShowTask),
+    /// Shows the tasks you should do next
+    Next(NextTasks),
     /// Marks a task as completed
     Done(CompleteTask),
     /// Marks a task as active
@@ -29,12 +74,43 @@ pub enum Commands {
     Edit(EditTask),
     /// Modify a task at the command line
     Modify(ModifyTask),
+    /// Links a task to another task
+    Link(LinkTasks),
+    /// Removes the links between two tasks
+    Unlink(UnlinkTasks),
+    /// Lists all tags with how many tasks have them
+    Tags,
+    /// Renames, merges or deletes tags across all tasks
+    Tag(TagCommand),
+    /// Manages areas that tasks can be grouped into
+    Area(AreaCommand),
+    /// Creates a list of tasks for the project in the current directory
+    Init(InitProject),
+    /// Moves tasks to another profile, keeping their uuids and history
+    Move(MoveTask),
+    /// Manages named profiles, each with its own tasks repository
+    Profile(ProfileCommand),
+    /// Shows or changes settings in the config file
+    Config(ConfigCommand),
     /// Passes git commands to the repository
     Git(GitExecute),
-    /// Pull then push to git remote and merge commits
+    /// Merges changes from the git remote, then pushes local changes to it
     Sync(SyncTasks),
+    /// Shows what hasn't been synced or committed yet
+    Status,
+    /// Shows how a task, or all tasks, changed over time
+    Log(LogTasks),
+    /// Lists tasks added, removed, completed or rescheduled between two dates or commits
+    Diff(DiffTasks),
     /// Undo a number of commits
     Undo(UndoExecute),
+    /// Redo a number of undone commits
+    Redo(RedoExecute),
+    /// Commit changes made with --no-commit
+    Commit(CommitChanges),
+    /// Merges tasks files, run by git when merging
+    #[command(hide = true)]
+    MergeDriver(MergeFiles),
 }
 
 #[derive(Args, PartialEq, Eq, Debug)]
@@ -52,6 +128,11 @@ pub struct CreateTask {
     #[clap(default_value=None)]
     pub tags: Option<String>,
 
+    /// Area the task belongs to
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub area: Option<String>,
+
     /// Date when you want to do the task
     #[arg(short, long)]
     #[clap(default_value=None)]
@@ -66,51 +147,86 @@ pub struct CreateTask {
     #[arg(short, long)]
     #[clap(default_value=None)]
     pub reminder: Option<String>,
+    /// How often the task repeats, such as "monthly", "every mon,thu" or "3 days after completion"
+    #[arg(short = 'R', long)]
+    #[clap(default_value=None)]
+    pub repeat: Option<String>,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct DeleteTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct ShowTask {
-    /// ID of the task
+    /// ID or UUID of the task, or a filter such as "status:pending tag:work -tag:someday"
+    #[arg(allow_hyphen_values = true)]
+    pub filter: Vec<String>,
+
+    /// Only show tasks in this area
+    #[arg(short, long)]
     #[clap(default_value=None)]
-    pub id: Option<usize>,
+    pub area: Option<String>,
+
+    /// Group tasks by their area
+    #[arg(short, long)]
+    pub group: bool,
+
+    /// Show the tasks as they were at a date, such as "last monday", or a commit
+    #[arg(long, value_name = "DATE|COMMIT")]
+    #[clap(default_value=None)]
+    pub at: Option<String>,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct NextTasks {
+    /// Number of tasks to show
+    #[clap(default_value = "5")]
+    pub number: usize,
+
+    /// Only recommend tasks matching a filter, such as "tag:work"
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub filter: Option<String>,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct CompleteTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct StartTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct StopTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct InboxTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct EditTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task
+    pub id: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct ModifyTask {
-    /// ID of the task
-    pub id: usize,
+    /// ID or UUID of the task, a list (1,4,7), a range (3-9) or a filter such as "tag:work"
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
 
-    /// Title of the task
-    #[clap(default_value=None)]
-    pub title: Option<String>,
+    /// New title of the task, and tags to add (+tag) or remove (-tag), after any options
+    #[arg(allow_hyphen_values = true)]
+    pub words: Vec<String>,
 
     /// Any notes to help explain/remember the task
     #[arg(short, long)]
@@ -122,6 +238,11 @@ pub struct ModifyTask {
     #[clap(default_value=None)]
     pub tags: Option<String>,
 
+    /// Area the task belongs to
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub area: Option<String>,
+
     /// Date when you want to do the task
     #[arg(short, long)]
     #[clap(default_value=None)]
@@ -136,21 +257,272 @@ pub struct ModifyTask {
     #[arg(short, long)]
     #[clap(default_value=None)]
     pub reminder: Option<String>,
+    /// How often the task repeats, or "never" to stop it repeating
+    #[arg(short = 'R', long)]
+    #[clap(default_value=None)]
+    pub repeat: Option<String>,
+}
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinkType {
+    /// The other task can't be done until this one is complete
+    Blocks,
+    /// This task can't be done until the other one is complete
+    BlockedBy,
+    /// The tasks are related to each other
+    Related,
+    /// This task is a duplicate of the other one
+    DuplicateOf,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct LinkTasks {
+    /// ID or UUID of the task
+    pub id: String,
+
+    /// How the task is linked to the other task
+    #[arg(value_enum)]
+    pub kind: LinkType,
+
+    /// ID or UUID of the other task
+    pub other: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct UnlinkTasks {
+    /// ID or UUID of the task
+    pub id: String,
+
+    /// ID or UUID of the other task
+    pub other: String,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct GitExecute {
-    /// Git command to run
-    pub command: String,
+    /// Git command to run, with its arguments
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+    pub args: Vec<String>,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct SyncTasks {
-    /// Git remote to use
-    #[clap(default_value = "origin")]
-    pub remote: String,
+    /// Git remote to use, repo.remote in the config file by default
+    pub remote: Option<String>,
+
+    /// Show what would be synced without changing anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct LogTasks {
+    /// ID or UUID of the task, or the full UUID of a deleted task, all tasks by default
+    pub id: Option<String>,
+
+    /// Only show changes since a date, or a span such as 3d, 1w or 2m
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub since: Option<String>,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct DiffTasks {
+    /// Date or commit to compare from, such as "last monday", 1w or HEAD~3
+    pub from: String,
+
+    /// Date or commit to compare to, the current tasks by default
+    pub to: Option<String>,
 }
 #[derive(Args, PartialEq, Eq, Debug)]
 pub struct UndoExecute {
     /// Number of times to undo
     #[clap(default_value = "1")]
-    pub number: String,
+    pub number: usize,
+
+    /// Revert changes even if they have already been synced
+    #[arg(short, long)]
+    pub force: bool,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct RedoExecute {
+    /// Number of times to redo
+    #[clap(default_value = "1")]
+    pub number: usize,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct CommitChanges {
+    /// Message for the commit, describing the changes by default
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub message: Option<String>,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct MergeFiles {
+    /// The tasks file both sides started from
+    pub base: PathBuf,
+
+    /// Our version of the tasks file, which is replaced with the merged tasks
+    pub ours: PathBuf,
+
+    /// Their version of the tasks file
+    pub theirs: PathBuf,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct AreaCommand {
+    #[clap(subcommand)]
+    pub command: AreaCommands,
+}
+#[derive(Subcommand, PartialEq, Eq, Debug)]
+pub enum AreaCommands {
+    /// Creates a new area
+    Add(CreateArea),
+    /// Lists all areas
+    List(ListAreas),
+    /// Renames an area, moving its tasks with it
+    Rename(RenameArea),
+    /// Archives an area, hiding it and its tasks
+    Archive(ArchiveArea),
+    /// Brings back an archived area
+    Unarchive(ArchiveArea),
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct CreateArea {
+    /// Name of the area
+    pub name: String,
+
+    /// What the area is for
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub description: Option<String>,
+
+    /// Tags given to new tasks in the area, separated by commas
+    #[arg(short, long)]
+    #[clap(default_value=None)]
+    pub tags: Option<String>,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct ListAreas {
+    /// Include archived areas
+    #[arg(short, long)]
+    pub all: bool,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct RenameArea {
+    /// Name of the area
+    pub name: String,
+
+    /// New name for the area
+    pub new_name: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct ArchiveArea {
+    /// Name of the area
+    pub name: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct TagCommand {
+    #[clap(subcommand)]
+    pub command: TagCommands,
+}
+#[derive(Subcommand, PartialEq, Eq, Debug)]
+pub enum TagCommands {
+    /// Renames a tag, and the tags nested under it
+    Rename(RenameTag),
+    /// Merges tags into another tag
+    Merge(MergeTags),
+    /// Removes a tag, and the tags nested under it, from every task
+    Delete(DeleteTag),
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct RenameTag {
+    /// Tag to rename
+    pub tag: String,
+
+    /// New name for the tag
+    pub new_tag: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct MergeTags {
+    /// Tags to merge
+    #[arg(required = true)]
+    pub tags: Vec<String>,
+
+    /// Tag to merge them into
+    #[arg(short, long)]
+    pub into: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct DeleteTag {
+    /// Tag to delete
+    pub tag: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct ConfigCommand {
+    #[clap(subcommand)]
+    pub command: ConfigCommands,
+}
+#[derive(Subcommand, PartialEq, Eq, Debug)]
+pub enum ConfigCommands {
+    /// Shows the value of a setting
+    Get(GetSetting),
+    /// Changes a setting
+    Set(SetSetting),
+    /// Puts a setting back to its default
+    Unset(GetSetting),
+    /// Lists every setting with its value
+    List,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct GetSetting {
+    /// Name of the setting, such as display.date_format
+    pub key: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct SetSetting {
+    /// Name of the setting, such as display.date_format
+    pub key: String,
+
+    /// New value, such as "%d/%m/%Y", true, id,title,deadline or -2.5
+    #[arg(allow_hyphen_values = true)]
+    pub value: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct MoveTask {
+    /// ID or UUID of the task, or a list (1,4,7), range (3-9) or filter
+    #[arg(allow_hyphen_values = true)]
+    pub id: String,
+
+    /// Profile to move the tasks to
+    #[arg(value_name = "PROFILE")]
+    pub to: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct ProfileCommand {
+    #[clap(subcommand)]
+    pub command: ProfileCommands,
+}
+#[derive(Subcommand, PartialEq, Eq, Debug)]
+pub enum ProfileCommands {
+    /// Adds a profile, or changes an existing one
+    Add(CreateProfile),
+    /// Lists all profiles
+    List,
+    /// Changes the profile used when --profile isn't given
+    Default(DefaultProfile),
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct CreateProfile {
+    /// Name of the profile
+    pub name: String,
+
+    /// Directory of the profile's tasks repository
+    pub path: PathBuf,
+
+    /// Git remote to sync the profile with
+    #[arg(short, long)]
+    pub remote: Option<String>,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct DefaultProfile {
+    /// Name of the profile, or "default" for the usual tasks repository
+    pub name: String,
+}
+#[derive(Args, PartialEq, Eq, Debug)]
+pub struct InitProject {
+    /// Keep the tasks in inertia.toml, instead of a .inertia directory
+    #[arg(short, long)]
+    pub file: bool,
 }
@@ -1,81 +1,219 @@
+mod areas;
+mod bulk;
 mod cmds;
+pub mod config;
 mod dates;
+mod editor;
 pub mod git;
+mod json;
+mod log;
 pub mod output;
+pub mod profiles;
+pub mod projects;
 mod tables;
+mod tags;
 
-use crate::args::{Commands, GitExecute, TasksArgs};
+use crate::args::{ArchiveArea, AreaCommand, AreaCommands, CreateArea, ListAreas, RenameArea};
 use crate::args::{
-    CompleteTask, CreateTask, DeleteTask, InboxTask, ModifyTask, ShowTask, StartTask, StopTask,
-    SyncTasks,
+    Commands, ConfigCommand, GitExecute, InitProject, MergeFiles, MoveTask, ProfileCommand,
+    TasksArgs,
 };
+use crate::args::{
+    CommitChanges, CompleteTask, CreateTask, DeleteTask, DiffTasks, EditTask, InboxTask, LinkTasks,
+    LogTasks, ModifyTask, NextTasks, RedoExecute, ShowTask, StartTask, StopTask, SyncTasks,
+    UndoExecute, UnlinkTasks,
+};
+use crate::args::{DeleteTag, MergeTags, RenameTag, TagCommand, TagCommands};
+use crate::error::InertiaError;
 use crate::repo;
-use crate::tasks::{Tasks, TasksError};
+use crate::tasks::Tasks;
 
-pub fn execute(tasks: &mut Tasks, arguments: TasksArgs) -> Result<(), TasksError> {
-    match arguments.command {
+/// Executes a command, returning a message describing the change if the tasks changed
+pub fn execute(tasks: &mut Tasks, arguments: TasksArgs) -> Result<Option<String>, InertiaError> {
+    let yes = arguments.yes;
+    let profile = arguments.profile;
+    let message = match arguments.command {
         Commands::Add(CreateTask {
             title,
             notes,
             tags,
+            area,
             when,
             deadline,
             reminder,
-        }) => {
-            cmds::add(tasks, title, notes, tags, when, deadline, reminder);
-        }
+            repeat,
+        }) => Some(cmds::add(
+            tasks, title, notes, tags, area, when, deadline, reminder, repeat,
+        )?),
 
         Commands::Modify(ModifyTask {
             id,
-            title,
+            words,
             notes,
             tags,
+            area,
             when,
             deadline,
             reminder,
+            repeat,
+        }) => Some(cmds::modify(
+            tasks, id, words, notes, tags, area, when, deadline, reminder, repeat, yes,
+        )?),
+
+        Commands::Del(DeleteTask { id }) => Some(cmds::delete(tasks, id, yes)?),
+
+        Commands::Done(CompleteTask { id }) => Some(cmds::done(tasks, id, yes)?),
+
+        Commands::Start(StartTask { id }) => Some(cmds::start(tasks, id, yes)?),
+
+        Commands::Stop(StopTask { id }) => Some(cmds::stop(tasks, id, yes)?),
+
+        Commands::Inbox(InboxTask { id }) => Some(cmds::inbox(tasks, id, yes)?),
+
+        Commands::Edit(EditTask { id }) => cmds::edit(tasks, id)?,
+
+        Commands::Clear => Some(cmds::clear(tasks)?),
+
+        Commands::Show(ShowTask {
+            filter,
+            area,
+            group,
+            at,
         }) => {
-            cmds::modify(tasks, id, title, notes, tags, when, deadline, reminder)?;
+            match at {
+                Some(at) => {
+                    projects::ensure_own_repo(tasks)?;
+                    let mut past = log::tasks_at(tasks, &at)?;
+                    cmds::show(&mut past, filter, area, group)?;
+                }
+                None => cmds::show(tasks, filter, area, group)?,
+            }
+            None
+        }
+
+        Commands::Next(NextTasks { number, filter }) => {
+            cmds::next(tasks, number, filter)?;
+            None
+        }
+
+        Commands::Link(LinkTasks { id, kind, other }) => Some(cmds::link(tasks, id, kind, other)?),
+
+        Commands::Unlink(UnlinkTasks { id, other }) => Some(cmds::unlink(tasks, id, other)?),
+
+        Commands::Tags => {
+            tags::list(tasks);
+            None
         }
 
-        Commands::Del(DeleteTask { id }) => {
-            cmds::delete(tasks, id)?;
+        Commands::Tag(TagCommand { command }) => match command {
+            TagCommands::Rename(RenameTag { tag, new_tag }) => {
+                Some(tags::rename(tasks, tag, new_tag)?)
+            }
+            TagCommands::Merge(MergeTags { tags, into }) => Some(tags::merge(tasks, tags, into)?),
+            TagCommands::Delete(DeleteTag { tag }) => Some(tags::delete(tasks, tag)?),
+        },
+
+        Commands::Area(AreaCommand { command }) => match command {
+            AreaCommands::Add(CreateArea {
+                name,
+                description,
+                tags,
+            }) => Some(areas::add(tasks, name, description, tags)?),
+            AreaCommands::List(ListAreas { all }) => {
+                areas::list(tasks, all);
+                None
+            }
+            AreaCommands::Rename(RenameArea { name, new_name }) => {
+                Some(areas::rename(tasks, name, new_name)?)
+            }
+            AreaCommands::Archive(ArchiveArea { name }) => Some(areas::archive(tasks, name, true)?),
+            AreaCommands::Unarchive(ArchiveArea { name }) => {
+                Some(areas::archive(tasks, name, false)?)
+            }
+        },
+
+        Commands::Move(MoveTask { id, to }) => {
+            // Tasks moved out of a project are described by where the project is
+            let from = if tasks.project {
+                tasks.path.display().to_string()
+            } else {
+                crate::config::get()
+                    .profile_name(profile.as_deref())
+                    .to_string()
+            };
+            Some(profiles::move_tasks(tasks, id, &from, to, yes)?)
         }
 
-        Commands::Done(CompleteTask { id }) => {
-            cmds::done(tasks, id)?;
+        Commands::Profile(ProfileCommand { command }) => {
+            profiles::execute(command)?;
+            None
         }
 
-        Commands::Start(StartTask { id }) => {
-            cmds::start(tasks, id)?;
+        Commands::Config(ConfigCommand { command }) => {
+            config::execute(command)?;
+            None
         }
 
-        Commands::Stop(StopTask { id }) => {
-            cmds::stop(tasks, id)?;
+        Commands::Git(GitExecute { args }) => {
+            repo::execute(&tasks.path, &args)?;
+            None
         }
 
-        Commands::Inbox(InboxTask { id }) => {
-            cmds::inbox(tasks, id)?;
+        Commands::Init(InitProject { file }) => {
+            projects::init(file)?;
+            None
         }
 
-        Commands::Clear => {
-            cmds::clear(tasks)?;
+        Commands::MergeDriver(MergeFiles { base, ours, theirs }) => {
+            git::merge_driver(&base, &ours, &theirs)?;
+            None
         }
 
-        Commands::Show(ShowTask { id }) => {
-            cmds::show(tasks, id)?;
+        Commands::Sync(SyncTasks { remote, dry_run }) => {
+            projects::ensure_own_repo(tasks)?;
+            let remote = match remote {
+                Some(remote) => remote,
+                None => crate::config::get().remote(profile.as_deref())?.to_string(),
+            };
+            git::sync(tasks, &remote, dry_run)?;
+            None
         }
 
-        Commands::Git(GitExecute { command }) => match repo::execute(&tasks.path, command) {
-            Ok(..) => (),
-            Err(..) => panic!("failed to execute git cmd"),
-        },
+        Commands::Status => {
+            projects::ensure_own_repo(tasks)?;
+            git::status(tasks, crate::config::get().profile_name(profile.as_deref()))?;
+            None
+        }
 
-        Commands::Sync(SyncTasks { remote }) => match repo::sync(&tasks.path, remote) {
-            Ok(..) => (),
-            Err(..) => panic!("failed"),
-        },
+        Commands::Log(LogTasks { id, since }) => {
+            projects::ensure_own_repo(tasks)?;
+            log::log(tasks, id, since)?;
+            None
+        }
+
+        Commands::Diff(DiffTasks { from, to }) => {
+            projects::ensure_own_repo(tasks)?;
+            log::diff(tasks, &from, to.as_deref())?;
+            None
+        }
+
+        Commands::Undo(UndoExecute { number, force }) => {
+            projects::ensure_own_repo(tasks)?;
+            git::undo(&tasks.path, number, force)?;
+            None
+        }
+
+        Commands::Redo(RedoExecute { number }) => {
+            projects::ensure_own_repo(tasks)?;
+            git::redo(&tasks.path, number)?;
+            None
+        }
 
-        _ => todo!(),
+        Commands::Commit(CommitChanges { message }) => {
+            projects::ensure_own_repo(tasks)?;
+            git::commit(&tasks.path, message)?;
+            None
+        }
     };
-    Ok(())
+    Ok(message)
 }
@@ -0,0 +1,79 @@
+use colored::Colorize;
+
+use crate::areas::Area;
+use crate::cli::cmds;
+use crate::cli::json;
+use crate::cli::output;
+use crate::cli::tables;
+use crate::error::InertiaError;
+use crate::tasks::Tasks;
+
+pub fn add(
+    tasks: &mut Tasks,
+    name: String,
+    description: Option<String>,
+    tags: Option<String>,
+) -> Result<String, InertiaError> {
+    // Parse the default tags for the area
+    let tags = cmds::parse_tags(tags);
+
+    // Add the new area
+    tasks.push_area(Area::new(name.clone(), description, tags))?;
+
+    // Success
+    if output::is_json() {
+        json::areas(tasks, &[tasks.area(&name)?]);
+    }
+    output::success(format!("created area: {}", name.blue()));
+    Ok(format!("area add: \"{}\"", name))
+}
+
+pub fn list(tasks: &Tasks, all: bool) {
+    // Archived areas are hidden unless asked for
+    let areas: Vec<&Area> = tasks
+        .areas
+        .iter()
+        .filter(|area| all || !area.archived)
+        .collect();
+
+    if output::is_json() {
+        json::areas(tasks, &areas);
+    } else if areas.is_empty() {
+        // Output when no areas are available
+        output::info(String::from("no areas found"))
+    } else {
+        // Generate the table of areas
+        let table = tables::areas_table(tasks, &areas);
+        // Print the table
+        println!("{}", table);
+    }
+}
+
+pub fn rename(tasks: &mut Tasks, name: String, new_name: String) -> Result<String, InertiaError> {
+    // Rename the area and everything in it
+    tasks.rename_area(&name, &new_name)?;
+
+    // Success
+    if output::is_json() {
+        json::areas(tasks, &[tasks.area(&new_name)?]);
+    }
+    output::success(format!(
+        "renamed area: {} to {}",
+        name.blue(),
+        new_name.blue()
+    ));
+    Ok(format!("area rename: \"{}\" -> \"{}\"", name, new_name))
+}
+
+pub fn archive(tasks: &mut Tasks, name: String, archived: bool) -> Result<String, InertiaError> {
+    // Archive or unarchive the area
+    tasks.area_mut(&name)?.archived = archived;
+
+    // Success
+    if output::is_json() {
+        json::areas(tasks, &[tasks.area(&name)?]);
+    }
+    let action = if archived { "archive" } else { "unarchive" };
+    output::success(format!("{}d area: {}", action, name.blue()));
+    Ok(format!("area {}: \"{}\"", action, name))
+}
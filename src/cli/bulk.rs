@@ -0,0 +1,115 @@
+use colored::Colorize;
+use std::io::{self, IsTerminal, Write};
+use uuid::Uuid;
+
+use crate::cli::json;
+use crate::cli::output;
+use crate::cli::tables;
+use crate::error::InertiaError;
+use crate::tasks::{Task, Tasks};
+
+/// Changing more tasks than this at once asks for confirmation first
+const CONFIRM_ABOVE: usize = 5;
+
+fn confirm(question: &str) -> Result<bool, InertiaError> {
+    // Asking on stderr keeps stdout for the tasks, which may be JSON
+    eprint!("{} {} [y/N] ", "confirm:".yellow().bold(), question);
+    io::stderr()
+        .flush()
+        .map_err(|_| InertiaError::cancelled())?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|_| InertiaError::cancelled())?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Finds the tasks to change, asking first if there are a lot of them
+pub fn select(tasks: &Tasks, id: &str, verb: &str, yes: bool) -> Result<Vec<Uuid>, InertiaError> {
+    let selected = tasks.select(id)?;
+    confirm_selected(tasks, selected, verb, yes)
+}
+
+/// Finds the tasks to change like `select`, except that filters leave out tasks already in the state they'd be put in
+pub fn select_changing(
+    tasks: &Tasks,
+    id: &str,
+    verb: &str,
+    yes: bool,
+    state: &str,
+    unchanged: fn(&Task) -> bool,
+) -> Result<Vec<Uuid>, InertiaError> {
+    let selected = tasks.select(id)?;
+    if id.split(',').all(|key| tasks.is_key(key)) {
+        return confirm_selected(tasks, selected, verb, yes);
+    }
+
+    // Filters often match old tasks too, such as completed instances of a repeating task
+    let (skipped, selected): (Vec<Uuid>, Vec<Uuid>) = selected.into_iter().partition(|uuid| {
+        tasks
+            .tasks
+            .iter()
+            .any(|task| task.uuid == *uuid && unchanged(task))
+    });
+    if selected.is_empty() {
+        return Err(InertiaError::unchanged(state));
+    } else if !skipped.is_empty() {
+        output::info(format!(
+            "skipped {} tasks that are already {}",
+            skipped.len(),
+            state
+        ));
+    }
+    confirm_selected(tasks, selected, verb, yes)
+}
+
+/// Asks before changing a lot of tasks at once
+fn confirm_selected(
+    tasks: &Tasks,
+    selected: Vec<Uuid>,
+    verb: &str,
+    yes: bool,
+) -> Result<Vec<Uuid>, InertiaError> {
+    if yes || selected.len() <= CONFIRM_ABOVE {
+        return Ok(selected);
+    }
+
+    // Nobody can answer when the input isn't a terminal
+    if !io::stdin().is_terminal() {
+        return Err(InertiaError::unconfirmed(selected.len()));
+    }
+
+    // Show what's about to change before asking
+    let shown: Vec<&Task> = tasks
+        .tasks
+        .iter()
+        .filter(|task| selected.contains(&task.uuid))
+        .collect();
+    output::message(tables::tasks_table(tasks, &shown).to_string());
+    if confirm(&format!("{} {} tasks?", verb, selected.len()))? {
+        Ok(selected)
+    } else {
+        Err(InertiaError::cancelled())
+    }
+}
+
+/// Prints the tasks that were changed, with a table if there were several, then any tasks created along the way
+pub fn report(tasks: &Tasks, verb: &str, changed: &[Task], created: &[Task]) {
+    if output::is_json() {
+        let shown: Vec<&Task> = changed.iter().chain(created).collect();
+        json::tasks(tasks, &shown);
+    }
+
+    match changed {
+        [task] => output::success(output::task_msg(verb, task)),
+        changed => {
+            output::success(format!("{} {} tasks", verb, changed.len()));
+            let shown: Vec<&Task> = changed.iter().collect();
+            output::message(tables::tasks_table(tasks, &shown).to_string());
+        }
+    }
+    for task in created {
+        output::info(output::task_msg("created next", task));
+    }
+}
@@ -1,29 +1,151 @@
+use chrono::Local;
+use colored::Colorize;
+
+use crate::args::LinkType;
+use crate::cli::bulk;
 use crate::cli::dates;
+use crate::cli::editor;
+use crate::cli::json;
 use crate::cli::output;
 use crate::cli::tables;
-use crate::tasks::{Task, Tasks, TasksError};
+use crate::config;
+use crate::error::InertiaError;
+use crate::filter::Filter;
+use crate::links::LinkKind;
+use crate::next;
+use crate::recurrence;
+use crate::tags;
+use crate::tasks::{Task, Tasks};
 
-fn parse_tags(tags: Option<String>) -> Option<Vec<String>> {
-    // Split tags into a vector by commas
-    tags.map(|tags| tags.split(',').map(str::to_string).collect())
+pub fn parse_tags(tags: Option<String>) -> Option<Vec<String>> {
+    // Split tags into a vector by commas, tidying them up
+    tags.map(|tags| tags::parse(&tags))
+        .filter(|tags| !tags.is_empty())
 }
 
-pub fn show(tasks: &mut Tasks, id: Option<usize>) -> Result<(), TasksError> {
-    // If no id is given, print out all tasks
-    if let Some(id) = id {
-        // Get the task the user wants to see
-        let task = tasks.task(id)?;
+struct Words {
+    title: Option<String>, // The new title, if any words weren't tag changes
+    added: Vec<String>,    // Tags to add, from +tag
+    removed: Vec<String>,  // Tags to remove, from -tag
+}
 
-        // Generate the table for the singular task
-        let table = tables::task_table(task, id);
-        // Print the table
-        println!("{}", table);
-    } else if tasks.is_empty() {
+fn parse_words(words: Vec<String>) -> Result<Words, InertiaError> {
+    let mut title = Vec::new();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+
+    for word in words {
+        if word.starts_with("--") || (word.starts_with('-') && word.chars().count() == 2) {
+            // Options after the title would otherwise be taken as part of it
+            return Err(InertiaError::misplaced_option(&word));
+        } else if let Some(tag) = word.strip_prefix('+').and_then(tags::normalise) {
+            added.push(tag);
+        } else if let Some(tag) = word.strip_prefix('-').and_then(tags::normalise) {
+            removed.push(tag);
+        } else {
+            title.push(word);
+        }
+    }
+
+    let title = if title.is_empty() {
+        None
+    } else {
+        Some(title.join(" "))
+    };
+    Ok(Words {
+        title,
+        added,
+        removed,
+    })
+}
+
+fn area_tags(tasks: &Tasks, area: &Option<String>) -> Result<Vec<String>, InertiaError> {
+    // Tasks can only be put in areas that exist and haven't been archived
+    if let Some(area) = area {
+        let area = tasks.area(area)?;
+        if area.archived {
+            return Err(InertiaError::area_archived(&area.name));
+        }
+        Ok(area.tags.clone().unwrap_or_default())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+pub fn show(
+    tasks: &mut Tasks,
+    filter: Vec<String>,
+    area: Option<String>,
+    group: bool,
+) -> Result<(), InertiaError> {
+    // If a single task is asked for, print out its details
+    if let [id] = filter.as_slice() {
+        if tasks.is_key(id) {
+            // Get the task the user wants to see
+            let index = tasks.position(id)?;
+            let task = &tasks.tasks[index];
+
+            if output::is_json() {
+                json::tasks(tasks, &[task]);
+            } else {
+                // Generate the table for the singular task
+                let table = tables::task_table(tasks, task);
+                // Print the table
+                println!("{}", table);
+            }
+            return Ok(());
+        }
+    }
+    let filter = Filter::parse(&filter)?;
+
+    // Pick out the tasks to show, hiding archived areas unless asked for one
+    let shown: Vec<&Task> = if let Some(area) = &area {
+        tasks.area(area)?;
+        tasks.area_tasks(area)
+    } else if filter.has_area() {
+        tasks.tasks.iter().collect()
+    } else {
+        tasks
+            .tasks
+            .iter()
+            .filter(|task| !tasks.in_archived_area(task))
+            .collect()
+    };
+    let shown: Vec<&Task> = shown
+        .into_iter()
+        .filter(|task| filter.matches(task))
+        .collect();
+
+    if output::is_json() {
+        // Scripts get every task, grouped or not
+        json::tasks(tasks, &shown);
+    } else if shown.is_empty() {
         // Output when no tasks are available
         output::info(String::from("no tasks found"))
+    } else if group {
+        // Print a table for each area, then one for tasks without an area
+        let mut groups: Vec<(String, Vec<&Task>)> = tasks
+            .areas
+            .iter()
+            .map(|area| {
+                let area_tasks = shown
+                    .iter()
+                    .filter(|task| task.area.as_ref() == Some(&area.name))
+                    .copied()
+                    .collect();
+                (area.name.clone(), area_tasks)
+            })
+            .collect();
+        let no_area = shown.iter().filter(|task| task.area.is_none()).copied();
+        groups.push((String::from("No area"), no_area.collect()));
+
+        for (name, group) in groups.iter().filter(|(_, group)| !group.is_empty()) {
+            println!("{}", name.magenta().bold());
+            println!("{}", tables::tasks_table(tasks, group));
+        }
     } else {
         // Generate the table of all tasks
-        let table = tables::tasks_table(tasks);
+        let table = tables::tasks_table(tasks, &shown);
         // Print the table
         println!("{}", table);
     };
@@ -32,127 +154,382 @@ pub fn show(tasks: &mut Tasks, id: Option<usize>) -> Result<(), TasksError> {
     Ok(())
 }
 
+pub fn next(tasks: &Tasks, number: usize, filter: Option<String>) -> Result<(), InertiaError> {
+    // Score the tasks to find the ones to do next
+    let filter = Filter::parse(filter.as_slice())?;
+    let now = Local::now().naive_local();
+    let recommendations = next::recommend(tasks, &filter, number, now);
+
+    if output::is_json() {
+        json::recommendations(tasks, &recommendations);
+    } else if recommendations.is_empty() {
+        // Output when there's nothing left to do
+        output::info(String::from("no tasks to do"))
+    } else {
+        // Generate the table of recommended tasks
+        let table = tables::next_table(tasks, &recommendations);
+        // Print the table
+        println!("{}", table);
+    }
+
+    // Success
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn add(
     tasks: &mut Tasks,
     title: String,
     notes: Option<String>,
     tags: Option<String>,
+    area: Option<String>,
     when: Option<String>,
     deadline: Option<String>,
     reminder: Option<String>,
-) {
-    // Parse dates and tags
-    let when = dates::parse_fuzzy_date(when);
-    let deadline = dates::parse_fuzzy_date(deadline);
-    let reminder = dates::parse_fuzzy_date(reminder);
+    repeat: Option<String>,
+) -> Result<String, InertiaError> {
+    // Use the configured defaults for anything that wasn't given
+    let defaults = &config::get().defaults;
+    let area = area.or_else(|| defaults.area.clone());
+    let when = when.or_else(|| defaults.when.clone());
+
+    // Parse dates, tags and how often the task repeats
+    let when = dates::parse_fuzzy_date(when)?;
+    let deadline = dates::parse_fuzzy_date(deadline)?;
+    let reminder = dates::parse_fuzzy_date(reminder)?;
     let tags = parse_tags(tags);
+    let recurrence = repeat
+        .map(|repeat| recurrence::parse(&repeat))
+        .transpose()?
+        .flatten();
+
+    // Tasks in an area get its default tags as well, along with the configured ones
+    let mut all_tags = area_tags(tasks, &area)?;
+    let default_tags = defaults.tags.iter().filter_map(|tag| tags::normalise(tag));
+    for tag in default_tags.chain(tags.into_iter().flatten()) {
+        if !all_tags.contains(&tag) {
+            all_tags.push(tag);
+        }
+    }
+    let tags = if all_tags.is_empty() {
+        None
+    } else {
+        Some(all_tags)
+    };
 
     // Generate a new task
-    let task = Task::new(title, notes, tags, when, deadline, reminder);
+    let mut task = Task::new(title, notes, tags, when, deadline, reminder);
+    task.area = area;
+    task.recurrence = recurrence;
     // Add the task to the tasks
-    tasks.push(task.clone());
-
-    // Calculate the id for output
-    let id = tasks.len() - 1;
+    let task = tasks.push(task).clone();
 
     // Success
-    output::success(output::task_msg("created", &task, id));
+    bulk::report(tasks, "created", std::slice::from_ref(&task), &[]);
+    Ok(output::commit_msg("add", &task))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn modify(
     tasks: &mut Tasks,
-    id: usize,
-    title: Option<String>,
+    id: String,
+    words: Vec<String>,
     notes: Option<String>,
     tags: Option<String>,
+    area: Option<String>,
     when: Option<String>,
     deadline: Option<String>,
     reminder: Option<String>,
-) -> Result<(), TasksError> {
-    // Parse dates and tags
-    let when = dates::parse_fuzzy_date(when);
-    let deadline = dates::parse_fuzzy_date(deadline);
-    let reminder = dates::parse_fuzzy_date(reminder);
+    repeat: Option<String>,
+    yes: bool,
+) -> Result<String, InertiaError> {
+    // Parse dates, tags and how often the task repeats
+    let when = dates::parse_fuzzy_date(when)?;
+    let deadline = dates::parse_fuzzy_date(deadline)?;
+    let reminder = dates::parse_fuzzy_date(reminder)?;
     let tags = parse_tags(tags);
+    let recurrence = repeat
+        .map(|repeat| recurrence::parse(&repeat))
+        .transpose()?;
+    let Words {
+        title,
+        added,
+        removed,
+    } = parse_words(words)?;
+    area_tags(tasks, &area)?;
 
-    // Get the task the user wants
-    let task = tasks.task(id)?;
+    // Get the tasks the user wants to modify
+    let selected = bulk::select(tasks, &id, "modify", yes)?;
+    if title.is_some() && selected.len() > 1 {
+        return Err(InertiaError::rename_many());
+    }
 
-    // If the the user changes the title, show that here
-    if title.is_some() {
-        output::info(output::task_msg("renaming task", task, id))
-    };
+    let mut modified = Vec::new();
+    let mut renamed = None;
+    for uuid in selected {
+        let index = tasks.index_of(uuid)?;
+        let task = &mut tasks.tasks[index];
 
-    // Modify the task
-    task.modify(title, notes, tags, when, deadline, reminder);
+        // If the the user changes the title, show that here
+        if title.is_some() {
+            output::info(output::task_msg("renaming task", task));
+            renamed = Some(task.title.clone());
+        };
+
+        // Modify the task
+        task.modify(
+            title.clone(),
+            notes.clone(),
+            tags.clone(),
+            when,
+            deadline,
+            reminder,
+        );
+        if area.is_some() {
+            task.area = area.clone();
+        }
+        if let Some(recurrence) = &recurrence {
+            task.recurrence = recurrence.clone();
+        }
+        for tag in &added {
+            task.add_tag(tag.clone());
+        }
+        for tag in &removed {
+            task.remove_tag(tag);
+        }
+        modified.push(task.clone());
+    }
 
     // Success
-    output::success(output::task_msg("modified", task, id));
-    Ok(())
+    bulk::report(tasks, "modified", &modified, &[]);
+    match (renamed, modified.as_slice()) {
+        (Some(old_title), [task]) => Ok(format!(
+            "rename: \"{}\" -> \"{}\" ({})",
+            old_title, task.title, task.id
+        )),
+        _ => Ok(output::commits_msg("modify", &modified)),
+    }
 }
 
-pub fn delete(tasks: &mut Tasks, id: usize) -> Result<(), TasksError> {
-    // Get the task the user wants to delete for output later
-    let mut binding = tasks.clone();
-    let task = binding.task(id)?;
+pub fn edit(tasks: &mut Tasks, id: String) -> Result<Option<String>, InertiaError> {
+    // Get the task the user wants to edit
+    let index = tasks.position(&id)?;
 
-    // Delete the task
-    tasks.remove(id)?;
+    // Let the user edit the task, only replacing it if something changed
+    let task = &tasks.tasks[index];
+    if let Some(mut edited) = editor::edit_task(tasks, task)? {
+        // Completing a repeating task in the editor gives its next occurrence, as with done
+        let mut created = Vec::new();
+        if edited.is_complete() && !task.is_complete() {
+            edited.status = task.status.clone();
+            let repeats = edited.recurrence.is_some();
+            match edited.complete() {
+                Some(next) => created.push(next),
+                None if repeats => output::warning(output::task_msg(
+                    "the next occurrence is too far in the future for",
+                    &edited,
+                )),
+                None => {}
+            }
+        }
+
+        tasks.tasks[index] = edited.clone();
+        let created: Vec<Task> = created
+            .into_iter()
+            .map(|next| tasks.push(next).clone())
+            .collect();
+        bulk::report(tasks, "edited", std::slice::from_ref(&edited), &created);
+        Ok(Some(output::commit_msg("edit", &edited)))
+    } else {
+        output::info(output::task_msg("no changes made to", task));
+        Ok(None)
+    }
+}
+
+pub fn link(
+    tasks: &mut Tasks,
+    id: String,
+    kind: LinkType,
+    other: String,
+) -> Result<String, InertiaError> {
+    // Get both of the tasks the user wants to link
+    let from = tasks.position(&id)?;
+    let to = tasks.position(&other)?;
+
+    // A task being blocked by another is stored as the other task blocking it
+    let (from, kind, to) = match kind {
+        LinkType::Blocks => (from, LinkKind::Blocks, to),
+        LinkType::BlockedBy => (to, LinkKind::Blocks, from),
+        LinkType::Related => (from, LinkKind::Related, to),
+        LinkType::DuplicateOf => (from, LinkKind::DuplicateOf, to),
+    };
+    tasks.link(from, kind, to)?;
 
     // Success
-    output::success(output::task_msg("deleted", task, id));
-    Ok(())
+    let (from, to) = (&tasks.tasks[from], &tasks.tasks[to]);
+    if output::is_json() {
+        json::tasks(tasks, &[from, to]);
+    }
+    output::success(format!(
+        "linked task: {}({}) {} {}({})",
+        from.title_string().blue(),
+        from.id_string().cyan(),
+        kind,
+        to.title_string().blue(),
+        to.id_string().cyan()
+    ));
+    Ok(format!(
+        "link: \"{}\" ({}) {} \"{}\" ({})",
+        from.title, from.id, kind, to.title, to.id
+    ))
+}
+
+pub fn unlink(tasks: &mut Tasks, id: String, other: String) -> Result<String, InertiaError> {
+    // Get both of the tasks the user wants to unlink
+    let a = tasks.position(&id)?;
+    let b = tasks.position(&other)?;
+
+    // Remove any links between them
+    if tasks.unlink(a, b) == 0 {
+        return Err(InertiaError::invalid_link("the tasks aren't linked"));
+    }
+
+    // Success
+    let (a, b) = (&tasks.tasks[a], &tasks.tasks[b]);
+    if output::is_json() {
+        json::tasks(tasks, &[a, b]);
+    }
+    output::success(format!(
+        "unlinked tasks: {}({}) and {}({})",
+        a.title_string().blue(),
+        a.id_string().cyan(),
+        b.title_string().blue(),
+        b.id_string().cyan()
+    ));
+    Ok(format!(
+        "unlink: \"{}\" ({}) and \"{}\" ({})",
+        a.title, a.id, b.title, b.id
+    ))
+}
+
+pub fn delete(tasks: &mut Tasks, id: String, yes: bool) -> Result<String, InertiaError> {
+    // Delete the tasks by uuid, as removing tasks moves the ones after them
+    let mut deleted = Vec::new();
+    for uuid in bulk::select(tasks, &id, "delete", yes)? {
+        deleted.push(tasks.remove(&uuid.to_string())?);
+    }
+
+    // Success
+    bulk::report(tasks, "deleted", &deleted, &[]);
+    Ok(output::commits_msg("del", &deleted))
 }
 
-pub fn clear(tasks: &mut Tasks) -> Result<(), TasksError> {
+pub fn clear(tasks: &mut Tasks) -> Result<String, InertiaError> {
     // Clear all tasks
+    let count = tasks.len();
+    if output::is_json() {
+        json::tasks(tasks, &tasks.tasks.iter().collect::<Vec<&Task>>());
+    }
     tasks.clear()?;
 
     // Success
     output::success(String::from("cleared all tasks"));
-    Ok(())
+    Ok(format!("clear: {} tasks", count))
 }
 
-pub fn stop(tasks: &mut Tasks, id: usize) -> Result<(), TasksError> {
-    // Get the task the user wants to stop
-    let task = tasks.task(id)?;
-    // Stop the task
-    task.stop();
+pub fn stop(tasks: &mut Tasks, id: String, yes: bool) -> Result<String, InertiaError> {
+    // Stop each of the tasks the user selected
+    let mut stopped = Vec::new();
+    let selected =
+        bulk::select_changing(tasks, &id, "stop", yes, "stopped", |task| !task.is_active())?;
+    for uuid in selected {
+        let index = tasks.index_of(uuid)?;
+        tasks.tasks[index].stop();
+        stopped.push(tasks.tasks[index].clone());
+    }
 
     // Success
-    output::success(output::task_msg("stopped", task, id));
-    Ok(())
+    bulk::report(tasks, "stopped", &stopped, &[]);
+    Ok(output::commits_msg("stop", &stopped))
 }
 
-pub fn start(tasks: &mut Tasks, id: usize) -> Result<(), TasksError> {
-    // Get the task the user wants to start
-    let task = tasks.task(id)?;
-    // Start the task
-    task.start();
+pub fn start(tasks: &mut Tasks, id: String, yes: bool) -> Result<String, InertiaError> {
+    // Start each of the tasks the user selected
+    let mut started = Vec::new();
+    let selected = bulk::select_changing(tasks, &id, "start", yes, "active or complete", |task| {
+        task.is_active() || task.is_complete()
+    })?;
+    for uuid in selected {
+        let index = tasks.index_of(uuid)?;
+        tasks.tasks[index].start();
+        started.push(tasks.tasks[index].clone());
+    }
 
     // Success
-    output::success(output::task_msg("started", task, id));
-    Ok(())
+    bulk::report(tasks, "started", &started, &[]);
+    Ok(output::commits_msg("start", &started))
 }
 
-pub fn done(tasks: &mut Tasks, id: usize) -> Result<(), TasksError> {
-    // Get the task the user wants to complete
-    let task = tasks.task(id)?;
-    // Complete the task
-    task.complete();
+pub fn done(tasks: &mut Tasks, id: String, yes: bool) -> Result<String, InertiaError> {
+    // Complete each of the tasks the user selected
+    let mut completed = Vec::new();
+    let mut created = Vec::new();
+    let mut skipped = 0;
+    let selected =
+        bulk::select_changing(tasks, &id, "complete", yes, "complete", Task::is_complete)?;
+    for uuid in selected {
+        let index = tasks.index_of(uuid)?;
+        // Completing a task twice would repeat it twice, so leave it as it is
+        if tasks.tasks[index].is_complete() {
+            skipped += 1;
+            continue;
+        }
+
+        // Completing a repeating task gives its next occurrence
+        let repeats = tasks.tasks[index].recurrence.is_some();
+        match tasks.tasks[index].complete() {
+            Some(next) => created.push(tasks.push(next).clone()),
+            None if repeats => output::warning(output::task_msg(
+                "the next occurrence is too far in the future for",
+                &tasks.tasks[index],
+            )),
+            None => {}
+        }
+        completed.push(tasks.tasks[index].clone());
+    }
+
+    if completed.is_empty() {
+        return Err(InertiaError::unchanged("complete"));
+    } else if skipped > 0 {
+        output::info(format!(
+            "skipped {} tasks that are already complete",
+            skipped
+        ));
+    }
 
     // Success
-    output::success(output::task_msg("completed", task, id));
-    Ok(())
+    bulk::report(tasks, "completed", &completed, &created);
+    Ok(output::commits_msg("done", &completed))
 }
 
-pub fn inbox(tasks: &mut Tasks, id: usize) -> Result<(), TasksError> {
-    // Get the task the user wants to return to the inbox
-    let task = tasks.task(id)?;
-    // Inbox the task
-    task.inbox();
+pub fn inbox(tasks: &mut Tasks, id: String, yes: bool) -> Result<String, InertiaError> {
+    // Return each of the tasks the user selected to the inbox
+    let mut inboxed = Vec::new();
+    let selected = bulk::select_changing(
+        tasks,
+        &id,
+        "inbox",
+        yes,
+        "in the inbox or complete",
+        |task| task.is_complete() || (task.is_inbox() && task.when.is_none()),
+    )?;
+    for uuid in selected {
+        let index = tasks.index_of(uuid)?;
+        tasks.tasks[index].inbox();
+        inboxed.push(tasks.tasks[index].clone());
+    }
 
     // Success
-    output::success(output::task_msg("inboxed", task, id));
-    Ok(())
+    bulk::report(tasks, "inboxed", &inboxed, &[]);
+    Ok(output::commits_msg("inbox", &inboxed))
 }
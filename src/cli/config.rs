@@ -0,0 +1,104 @@
+use colored::Colorize;
+
+use crate::args::ConfigCommands;
+use crate::cli::json::{self, SettingJson};
+use crate::cli::output;
+use crate::cli::tables;
+use crate::config::{self, KEYS};
+use crate::error::InertiaError;
+
+pub fn execute(command: ConfigCommands) -> Result<(), InertiaError> {
+    match command {
+        ConfigCommands::Get(get) => self::get(get.key),
+        ConfigCommands::Set(set) => self::set(set.key, set.value),
+        ConfigCommands::Unset(unset) => self::unset(unset.key),
+        ConfigCommands::List => list(),
+    }
+}
+
+fn description(key: &str) -> &'static str {
+    KEYS.iter()
+        .find(|(known, _)| *known == key)
+        .map(|(_, description)| *description)
+        .unwrap_or_default()
+}
+
+pub fn get(key: String) -> Result<(), InertiaError> {
+    config::load()?;
+    let value = config::value(config::get(), &key)?;
+
+    if output::is_json() {
+        json::settings(vec![SettingJson {
+            description: description(&key),
+            key,
+            value,
+        }]);
+    } else {
+        // Print strings without quotes, so they can be used in scripts
+        match value {
+            Some(toml::Value::String(value)) => println!("{}", value),
+            Some(value) => println!("{}", value),
+            None => output::info(format!("{} isn't set", key)),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn set(key: String, value: String) -> Result<(), InertiaError> {
+    let value = config::set(&key, &value)?;
+
+    if output::is_json() {
+        json::settings(vec![SettingJson {
+            description: description(&key),
+            key: key.clone(),
+            value: Some(value.clone()),
+        }]);
+    }
+
+    // Success
+    output::success(format!(
+        "set {} to {}",
+        key.cyan(),
+        value.to_string().blue()
+    ));
+    Ok(())
+}
+
+pub fn unset(key: String) -> Result<(), InertiaError> {
+    config::unset(&key)?;
+
+    // Success
+    output::success(format!("{} is back to its default", key.cyan()));
+    Ok(())
+}
+
+pub fn list() -> Result<(), InertiaError> {
+    config::load()?;
+    let settings: Vec<(&str, Option<toml::Value>, &str)> = KEYS
+        .iter()
+        .map(|(key, description)| Ok((*key, config::value(config::get(), key)?, *description)))
+        .collect::<Result<_, InertiaError>>()?;
+
+    if output::is_json() {
+        json::settings(
+            settings
+                .into_iter()
+                .map(|(key, value, description)| SettingJson {
+                    key: key.to_string(),
+                    value,
+                    description,
+                })
+                .collect(),
+        );
+    } else {
+        // Print the table of settings
+        println!("{}", tables::settings_table(&settings));
+        output::info(format!(
+            "settings are read from {}",
+            config::path()?.display()
+        ));
+    }
+
+    Ok(())
+}
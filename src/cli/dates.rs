@@ -1,13 +1,36 @@
-use chrono::NaiveDateTime;
-use colored::Colorize;
+use chrono::{Duration, Local, Months, NaiveDateTime};
 
-pub fn parse_fuzzy_date(date_string: Option<String>) -> Option<NaiveDateTime> {
+use crate::error::InertiaError;
+
+pub fn parse_fuzzy_date(
+    date_string: Option<String>,
+) -> Result<Option<NaiveDateTime>, InertiaError> {
     if let Some(date_string) = date_string {
-        match fuzzydate::parse(date_string) {
-            Ok(date) => Some(date),
-            Err(err) => panic!("{} {:?}", "error:".red().bold(), err),
+        match fuzzydate::parse(&date_string) {
+            Ok(date) => Ok(Some(date)),
+            Err(_) => Err(InertiaError::invalid_date(&date_string)),
         }
     } else {
-        None
+        Ok(None)
     }
 }
+
+/// Parses how far back to look, either as a short span such as "3d" or "1w", or as any fuzzy date
+pub fn parse_since(since: &str) -> Result<NaiveDateTime, InertiaError> {
+    let now = Local::now().naive_local();
+    let split = since.len() - since.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let (number, unit) = since.split_at(split);
+
+    let date = match (number.parse::<u32>(), unit) {
+        (Ok(number), "h") => now.checked_sub_signed(Duration::hours(number.into())),
+        (Ok(number), "d") => now.checked_sub_signed(Duration::days(number.into())),
+        (Ok(number), "w") => now.checked_sub_signed(Duration::weeks(number.into())),
+        // Months rather than minutes, since tasks change over days and weeks
+        (Ok(number), "m") => now.checked_sub_months(Months::new(number)),
+        (Ok(number), "y") => number
+            .checked_mul(12)
+            .and_then(|months| now.checked_sub_months(Months::new(months))),
+        _ => return fuzzydate::parse(since).map_err(|_| InertiaError::invalid_date(since)),
+    };
+    date.ok_or_else(|| InertiaError::invalid_date(since))
+}
@@ -0,0 +1,143 @@
+use std::env;
+use std::fs::{self, DirBuilder};
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::tags;
+use crate::tasks::{Task, Tasks};
+
+const HELP: &str = "\
+# Save and close the editor to apply your changes, or leave it unchanged to cancel.
+# Dates are written like \"2023-01-31T09:00:00\". The uuid and id can't be changed.
+# Lines starting with '#' are ignored.
+";
+
+fn editor() -> String {
+    // Prefer $VISUAL, then $EDITOR, then fall back to vi
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from("vi"))
+}
+
+fn open_editor(path: &Path) -> Result<(), InertiaError> {
+    // The editor may come with its own arguments, such as "code --wait"
+    let editor = editor();
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| InertiaError::edit_failed("no editor set"))?;
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|err| InertiaError::edit_failed(&format!("couldn't run {}: {}", editor, err)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(InertiaError::edit_failed(&format!(
+            "{} exited with {}",
+            editor, status
+        )))
+    }
+}
+
+fn strip_header(text: &str) -> String {
+    // Drop the help and error lines we put at the top of the document
+    let body: Vec<&str> = text
+        .lines()
+        .skip_while(|line| line.starts_with('#') || line.trim().is_empty())
+        .collect();
+    format!("{}\n", body.join("\n"))
+}
+
+fn parse(text: &str, tasks: &Tasks, original: &Task) -> Result<Task, String> {
+    let mut task: Task = toml::from_str(text).map_err(|err| err.to_string())?;
+
+    // Tags are tidied the same way as when they're given on the command line
+    task.tags = task.tags.map(|tags| tags::parse(&tags.join(",")));
+
+    if task.uuid != original.uuid {
+        Err(String::from("the uuid of a task can't be changed"))
+    } else if task.id != original.id {
+        Err(String::from("the id of a task can't be changed"))
+    } else if task.title.trim().is_empty() {
+        Err(String::from("the title of a task can't be empty"))
+    } else {
+        // Tasks can only be moved into areas that exist
+        if task.area != original.area {
+            if let Some(area) = &task.area {
+                tasks.area(area).map_err(|err| err.to_string())?;
+            }
+        }
+        tasks.check_links(&task).map_err(|err| err.to_string())?;
+        Ok(task)
+    }
+}
+
+fn serialize(task: &Task) -> Result<String, InertiaError> {
+    toml::to_string_pretty(task).map_err(|err| InertiaError::edit_failed(&err.to_string()))
+}
+
+fn edit_loop(path: &Path, tasks: &Tasks, task: &Task) -> Result<Option<Task>, InertiaError> {
+    let original = serialize(task)?;
+    let mut document = format!("{}\n{}", HELP, original);
+
+    loop {
+        // Write out the document and let the user edit it
+        fs::write(path, &document).map_err(|err| InertiaError::edit_failed(&err.to_string()))?;
+        open_editor(path)?;
+        let edited =
+            fs::read_to_string(path).map_err(|err| InertiaError::edit_failed(&err.to_string()))?;
+
+        // Closing the editor without saving anything cancels the edit
+        if edited == document {
+            return Ok(None);
+        }
+
+        match parse(&edited, tasks, task) {
+            Ok(edited) if serialize(&edited)? == original => return Ok(None),
+            Ok(edited) => return Ok(Some(edited)),
+            Err(err) => {
+                // Reopen the editor with the error shown above the user's changes
+                let err: String = err.lines().map(|line| format!("# {}\n", line)).collect();
+                document = format!(
+                    "# error: the task is invalid, fix it or leave it unchanged to cancel\n{}{}\n{}",
+                    err,
+                    HELP,
+                    strip_header(&edited)
+                );
+            }
+        }
+    }
+}
+
+/// Makes a directory only we can use, so other users can't swap the file being edited for a link elsewhere
+fn private_dir() -> Result<PathBuf, InertiaError> {
+    let dir = env::temp_dir().join(format!("inertia-{}", Uuid::new_v4()));
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+
+    // Creating the directory fails if anything already exists there
+    builder
+        .create(&dir)
+        .map_err(|err| InertiaError::edit_failed(&err.to_string()))?;
+    Ok(dir)
+}
+
+/// Opens a task in the user's editor, returning the edited task if anything changed
+pub fn edit_task(tasks: &Tasks, task: &Task) -> Result<Option<Task>, InertiaError> {
+    let dir = private_dir()?;
+    let result = edit_loop(&dir.join(format!("{}.toml", task.uuid)), tasks, task);
+
+    // The temporary files are no longer needed, even if editing failed
+    let _ = fs::remove_dir_all(&dir);
+
+    result
+}
@@ -1 +1,300 @@
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
 
+use crate::cli::json::{self, StatusJson, SyncJson};
+use crate::cli::{output, tables};
+use crate::error::InertiaError;
+use crate::git::Repository;
+use crate::history::{self, Changes};
+use crate::merge;
+use crate::repo;
+use crate::tasks::Tasks;
+
+fn ensure_clean(repo: &Repository) -> Result<(), InertiaError> {
+    if repo.is_clean()? {
+        Ok(())
+    } else {
+        Err(InertiaError::history(
+            "the tasks repository has uncommitted changes, commit them with `inertia commit` first",
+        ))
+    }
+}
+
+pub fn undo(path: &Path, number: usize, force: bool) -> Result<(), InertiaError> {
+    let repo = Repository::open(path)?;
+    ensure_clean(&repo)?;
+
+    // The first commit creates the repository, so it can't be undone
+    let commits = repo.count("HEAD")?;
+    if number == 0 || number >= commits {
+        return Err(InertiaError::history(&format!(
+            "can't undo {} changes, there are only {} to undo",
+            number,
+            commits.saturating_sub(1)
+        )));
+    }
+
+    let target = format!("HEAD~{}", number);
+    let oldest = format!("HEAD~{}", number - 1);
+    let reverted = repo.subjects(&target, "HEAD")?;
+
+    // Changes that were pushed to a remote can't simply be dropped from history
+    let pushed = repo.is_pushed(&oldest)?;
+
+    let action = if pushed && !force {
+        return Err(InertiaError::history(
+            "can't undo changes that have already been synced, use --force to revert them",
+        ));
+    } else if pushed {
+        // Revert the changes in a new commit that can be synced like any other
+        let message = format!("undo: {}", reverted.join(", "));
+        repo.revert_to(&target, &message)?;
+        repo::clear_redo(&repo)?;
+        output::warning(String::from(
+            "reverted changes that were already synced, they can't be redone",
+        ));
+        "reverted"
+    } else {
+        // Remember where we were, unless we're already part way through undoing
+        let redo = repo::redo_tip(&repo)?;
+        let continuing = match redo {
+            Some(tip) => repo.is_ancestor("HEAD", &tip)?,
+            None => false,
+        };
+        if !continuing {
+            repo::set_redo(&repo, "HEAD")?;
+        }
+
+        repo.reset_hard(&target)?;
+        "undone"
+    };
+
+    if output::is_json() {
+        json::undone(action, &reverted);
+    }
+    for subject in reverted {
+        output::success(format!("undid {}", subject.blue()));
+    }
+    Ok(())
+}
+
+pub fn redo(path: &Path, number: usize) -> Result<(), InertiaError> {
+    let repo = Repository::open(path)?;
+    ensure_clean(&repo)?;
+
+    // Find the undone commits between HEAD and the redo tip, oldest first
+    let tip = repo::redo_tip(&repo)?;
+    let undone: Vec<String> = match tip {
+        Some(tip) if repo.is_ancestor("HEAD", &tip)? => {
+            let mut undone = repo.commits("HEAD", &tip)?;
+            undone.reverse();
+            undone
+        }
+        _ => Vec::new(),
+    };
+
+    if undone.is_empty() {
+        return Err(InertiaError::history("there is nothing to redo"));
+    } else if number == 0 || number > undone.len() {
+        return Err(InertiaError::history(&format!(
+            "can't redo {} changes, there are only {} to redo",
+            number,
+            undone.len()
+        )));
+    }
+
+    let target = &undone[number - 1];
+    let mut redone = repo.subjects("HEAD", target)?;
+    redone.reverse();
+
+    repo.reset_hard(target)?;
+
+    if output::is_json() {
+        json::undone("redone", &redone);
+    }
+    for subject in redone {
+        output::success(format!("redid {}", subject.blue()));
+    }
+    Ok(())
+}
+
+pub fn commit(path: &Path, message: Option<String>) -> Result<(), InertiaError> {
+    let batch = repo::batch(path)?;
+
+    // Describe the batch unless the user gave their own message
+    let message = match (message, batch.as_slice()) {
+        (Some(message), _) => message,
+        (None, [change]) => change.clone(),
+        (None, changes) => format!("batch: {} changes", changes.len()),
+    };
+
+    if repo::commit(path, &message)? {
+        output::success(format!("committed {}", message.blue()));
+    } else {
+        output::info(String::from("nothing to commit"));
+    }
+    Ok(())
+}
+
+/// Merges tasks files for git, writing the result over our version as git expects
+pub fn merge_driver(base: &Path, ours: &Path, theirs: &Path) -> Result<(), InertiaError> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|err| InertiaError::io(path, &err.to_string()))
+    };
+    let (data, conflicts) = merge::merge_data(ours, &read(base)?, &read(ours)?, &read(theirs)?)?;
+
+    // Git keeps its own copy of each side, so there's no need for a backup
+    fs::write(ours, data).map_err(|err| InertiaError::io(ours, &err.to_string()))?;
+
+    if conflicts == 0 {
+        output::success(String::from("merged tasks"));
+    } else {
+        output::warning(format!(
+            "merged tasks, but {} tasks were changed differently on each side, see inertia show tag:{}",
+            conflicts,
+            merge::CONFLICT_TAG
+        ));
+    }
+    Ok(())
+}
+
+/// Reports changes between HEAD and another branch in terms of tasks
+fn report(changes: Changes, commits: usize, side: &str) {
+    if commits > 0 {
+        let plural = if commits == 1 { "" } else { "s" };
+        output::info(format!(
+            "{} {}, in {} commit{}",
+            changes.to_string().blue(),
+            side,
+            commits,
+            plural
+        ));
+    }
+}
+
+pub fn sync(tasks: &Tasks, remote: &str, dry_run: bool) -> Result<(), InertiaError> {
+    let path = &tasks.path;
+    let repo = Repository::open(path)?;
+
+    // Changes that haven't been committed would be left out, or stop the merge
+    if !repo.is_clean()? {
+        let pending = history::changes(
+            history::tasks_at(&repo, &tasks.file, "HEAD")?.as_ref(),
+            Some(tasks),
+        );
+        if dry_run {
+            output::info(format!("would commit {} first", pending.to_string().blue()));
+        } else {
+            // Batched changes describe themselves, anything else was changed outside inertia
+            let message = if repo::batch(path)?.is_empty() {
+                Some(format!("commit before sync: {}", pending))
+            } else {
+                None
+            };
+            commit(path, message)?;
+        }
+    }
+
+    // Fetching only updates the remote branch, so it's safe for a dry run too
+    repo.fetch(remote)?;
+    let branch = repo.branch()?;
+    let upstream = format!("{}/{}", remote, branch);
+
+    let (ahead, behind, incoming, outgoing) = if repo.resolve(&upstream)?.is_some() {
+        let base = repo.merge_base(&upstream)?.ok_or_else(|| {
+            InertiaError::history(&format!(
+                "{} doesn't share any history with {}",
+                upstream, branch
+            ))
+        })?;
+        let (ahead, behind) = repo.ahead_behind(&upstream)?;
+        let incoming = history::changes_between(&repo, &tasks.file, Some(&base), &upstream)?;
+        let outgoing = history::changes_between(&repo, &tasks.file, Some(&base), "HEAD")?;
+        report(incoming, behind, "remotely");
+        report(outgoing, ahead, "locally");
+        (ahead, behind, incoming, outgoing)
+    } else {
+        // Nothing has been pushed yet, so everything is new to the remote
+        let ahead = repo.count("HEAD")?;
+        let outgoing = history::changes_between(&repo, &tasks.file, None, "HEAD")?;
+        report(outgoing, ahead, "locally");
+        (ahead, 0, Changes::default(), outgoing)
+    };
+
+    let mut synced = SyncJson {
+        remote: upstream.clone(),
+        incoming,
+        outgoing,
+        pulled: 0,
+        pushed: 0,
+        conflicts: 0,
+        dry_run,
+    };
+
+    if ahead == 0 && behind == 0 {
+        output::success(format!("already in sync with {}", upstream.blue()));
+    } else if dry_run {
+        output::info(String::from("dry run, nothing was synced"));
+    } else {
+        // The tasks are merged task by task, adding conflict tasks for clashing changes
+        if behind > 0 {
+            // Clones need the merge driver set up, since git config isn't cloned
+            repo::set_up_repo(&tasks.location())?;
+            let before = repo.head()?.unwrap_or_default();
+            repo::merge(path, &tasks.file, &upstream)?;
+            let merged = history::changes_between(&repo, &tasks.file, Some(&before), "HEAD")?;
+            synced.pulled = behind;
+            synced.conflicts = merged.conflicts;
+            if merged.conflicts > 0 {
+                output::warning(format!(
+                    "{} tasks were changed differently on each side, see inertia show tag:{}",
+                    merged.conflicts,
+                    merge::CONFLICT_TAG
+                ));
+            }
+        }
+        if ahead > 0 {
+            repo.push(remote, &branch)?;
+            synced.pushed = ahead;
+        }
+
+        output::success(format!("synced with {}", upstream.blue()));
+    }
+
+    if output::is_json() {
+        json::sync(synced);
+    }
+    Ok(())
+}
+
+pub fn status(tasks: &Tasks, profile: &str) -> Result<(), InertiaError> {
+    let path = &tasks.path;
+    let repo = Repository::open(path)?;
+
+    // Ahead and behind are as of the last fetch, so this works offline
+    let upstream = repo.upstream()?;
+    let (ahead, behind) = match &upstream {
+        Some(upstream) => repo.ahead_behind(upstream)?,
+        None => (0, 0),
+    };
+    let committed = history::tasks_at(&repo, &tasks.file, "HEAD")?;
+
+    let status = StatusJson {
+        profile: profile.to_string(),
+        path: path.clone(),
+        branch: repo.branch()?,
+        upstream,
+        ahead,
+        behind,
+        uncommitted: history::changes(committed.as_ref(), Some(tasks)),
+        batched: repo::batch(path)?.len(),
+    };
+
+    if output::is_json() {
+        json::status(status);
+    } else {
+        println!("{}", tables::status_table(&status));
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::areas::Area;
+use crate::args::OutputFormat;
+use crate::cli::output;
+use crate::history::{Changes, Event, FieldChange, FieldValue, TaskDiff};
+use crate::next::Recommendation;
+use crate::tasks::{Task, Tasks};
+
+// The JSON written here is relied on by scripts, so fields can be added but never renamed or removed
+
+fn iso_date(date: Option<NaiveDateTime>) -> Option<String> {
+    date.map(|date| date.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+#[derive(Serialize)]
+pub struct LinkJson {
+    pub kind: &'static str, // How the task is linked, such as "blocks" or "blocked by"
+    pub id: usize,          // The id of the linked task
+    pub uuid: String,       // The uuid of the linked task
+}
+
+#[derive(Serialize)]
+pub struct TaskJson {
+    pub id: usize,             // Short id, which stays the same until the task is deleted
+    pub uuid: String,          // Unique identifier that never changes
+    pub title: String,         // The title of the task
+    pub status: &'static str,  // "inbox", "pending", "active" or "complete"
+    pub blocked: bool,         // Whether uncompleted tasks are blocking it
+    pub notes: Option<String>, // Any notes, or null
+    pub area: Option<String>,  // The name of its area, or null
+    pub tags: Vec<String>,     // Its tags, which may be empty
+    pub when: Option<String>,  // When to do it, as an ISO 8601 date, or null
+    pub deadline: Option<String>, // When it has to be done by, or null
+    pub reminder: Option<String>, // When to be reminded about it, or null
+    pub created: Option<String>, // When it was created, or null for older tasks
+    pub recurrence: Option<String>, // How often it repeats, such as "every 2 weeks", or null
+    pub links: Vec<LinkJson>,  // Links to and from other tasks
+}
+
+impl TaskJson {
+    pub fn new(tasks: &Tasks, task: &Task) -> Self {
+        let links = tasks
+            .links(task)
+            .into_iter()
+            .map(|(kind, other)| LinkJson {
+                kind,
+                id: other.id,
+                uuid: other.uuid_string(),
+            })
+            .collect();
+
+        Self {
+            id: task.id,
+            uuid: task.uuid_string(),
+            title: task.title.clone(),
+            status: task.status.as_str(),
+            blocked: !task.is_complete() && tasks.is_blocked(task),
+            notes: task.notes.clone(),
+            area: task.area.clone(),
+            tags: task.tags.clone().unwrap_or_default(),
+            when: iso_date(task.when),
+            deadline: iso_date(task.deadline),
+            reminder: iso_date(task.reminder),
+            created: iso_date(task.created),
+            recurrence: task.recurrence.as_ref().map(|rule| rule.to_string()),
+            links,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RecommendationJson {
+    #[serde(flatten)]
+    pub task: TaskJson, // The recommended task
+    pub score: f64,           // Higher scores should be done first
+    pub reasons: Vec<String>, // Why the task scored highly, most important first
+}
+
+#[derive(Serialize)]
+pub struct AreaJson {
+    pub name: String,                // The unique name of the area
+    pub description: Option<String>, // What the area is for, or null
+    pub archived: bool,              // Whether the area is hidden
+    pub tags: Vec<String>,           // Tags given to new tasks in the area
+    pub tasks: usize,                // How many tasks are in the area
+    pub remaining: usize,            // How many of them aren't complete
+}
+
+#[derive(Serialize)]
+pub struct TagJson {
+    pub tag: String,  // The full name of the tag, such as "work/client-a"
+    pub count: usize, // How many tasks have the tag, or a tag nested under it
+}
+
+#[derive(Serialize)]
+pub struct TagChangeJson {
+    pub tags: Vec<String>,    // The tags that were renamed, merged or deleted
+    pub into: Option<String>, // The tag they became, or null if they were deleted
+    pub tasks: usize,         // How many tasks were changed
+}
+
+#[derive(Serialize)]
+pub struct SettingJson {
+    pub key: String, // The name of the setting, such as "display.date_format"
+    pub value: Option<toml::Value>, // Its value, or null if it isn't set
+    pub description: &'static str, // What the setting is for
+}
+
+#[derive(Serialize)]
+pub struct ProfileJson {
+    pub name: String, // The name of the profile, "default" for the usual tasks repository
+    pub path: PathBuf, // Directory of the profile's tasks repository
+    pub remote: String, // Git remote the profile syncs with
+    pub default: bool, // Whether it's used when --profile isn't given
+}
+
+#[derive(Serialize)]
+pub struct StatusJson {
+    pub profile: String, // The profile in use, "default" for the usual tasks repository
+    pub path: PathBuf,   // Directory of the tasks repository
+    pub branch: String,  // The branch that is checked out
+    pub upstream: Option<String>, // The remote branch it syncs with, or null before the first sync
+    pub ahead: usize,    // Commits that haven't been synced yet
+    pub behind: usize,   // Commits on the remote as of the last sync that haven't been merged
+    pub uncommitted: Changes, // Changes to tasks that haven't been committed
+    pub batched: usize,  // Changes made with --no-commit
+}
+
+#[derive(Serialize)]
+pub struct SyncJson {
+    pub remote: String,    // The remote branch synced with, such as "origin/master"
+    pub incoming: Changes, // Changes to tasks that were on the remote but not here
+    pub outgoing: Changes, // Changes to tasks that were here but not on the remote
+    pub pulled: usize,     // Commits merged from the remote
+    pub pushed: usize,     // Commits pushed to the remote
+    pub conflicts: usize,  // Conflict tasks made for changes that clashed
+    pub dry_run: bool,     // Whether this only shows what would be synced
+}
+
+#[derive(Serialize)]
+pub struct UndoJson {
+    pub action: &'static str, // "undone", "reverted" for changes undone in a new commit, or "redone"
+    pub message: String,      // The change, as described in its commit
+}
+
+#[derive(Serialize)]
+pub struct FieldChangeJson {
+    pub field: &'static str, // The field that changed, such as "deadline" or "status"
+    pub before: Option<String>, // What it was, with dates in ISO 8601, or null if it wasn't set
+    pub after: Option<String>, // What it became, or null if it was cleared
+}
+
+#[derive(Serialize)]
+pub struct EventJson {
+    pub commit: String,                // The id of the commit that made the change
+    pub date: String,                  // When it was committed, as an ISO 8601 date
+    pub message: String,               // The commit message
+    pub uuid: String,                  // The uuid of the task that changed
+    pub id: usize,                     // Its short id at the time
+    pub title: String,                 // Its title at the time
+    pub kind: &'static str,            // "created", "deleted", "changed" or "moved"
+    pub changes: Vec<FieldChangeJson>, // The fields that changed, or were set when it was created
+    pub moved_from: Option<PathBuf>,   // The repository a moved task came from, or null
+}
+
+#[derive(Serialize)]
+pub struct DiffJson {
+    pub change: &'static str, // "added", "removed", "completed", "reopened" or "rescheduled"
+    #[serde(flatten)]
+    pub task: TaskJson, // The task, as it was before if it was removed
+    pub changes: Vec<FieldChangeJson>, // The dates that changed if it was rescheduled
+}
+
+fn field_value(value: &Option<FieldValue>) -> Option<String> {
+    match value {
+        Some(FieldValue::Text(text)) => Some(text.clone()),
+        Some(FieldValue::Date(date)) => iso_date(Some(*date)),
+        None => None,
+    }
+}
+
+fn field_changes(changes: &[FieldChange]) -> Vec<FieldChangeJson> {
+    changes
+        .iter()
+        .map(|change| FieldChangeJson {
+            field: change.field,
+            before: field_value(&change.before),
+            after: field_value(&change.after),
+        })
+        .collect()
+}
+
+/// Prints a list of items as a JSON array, or one JSON object per line
+fn emit<T: Serialize>(items: &[T]) {
+    if output::format() == OutputFormat::Ndjson {
+        for item in items {
+            println!(
+                "{}",
+                serde_json::to_string(item).expect("tasks can always be written as JSON")
+            );
+        }
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(items).expect("tasks can always be written as JSON")
+        );
+    }
+}
+
+pub fn tasks(tasks: &Tasks, shown: &[&Task]) {
+    let shown: Vec<TaskJson> = shown
+        .iter()
+        .map(|task| TaskJson::new(tasks, task))
+        .collect();
+    emit(&shown);
+}
+
+pub fn recommendations(tasks: &Tasks, recommendations: &[Recommendation]) {
+    let recommendations: Vec<RecommendationJson> = recommendations
+        .iter()
+        .map(|recommendation| RecommendationJson {
+            task: TaskJson::new(tasks, &tasks.tasks[recommendation.index]),
+            score: recommendation.score,
+            reasons: recommendation.reasons.clone(),
+        })
+        .collect();
+    emit(&recommendations);
+}
+
+pub fn areas(tasks: &Tasks, areas: &[&Area]) {
+    let areas: Vec<AreaJson> = areas
+        .iter()
+        .map(|area| {
+            let area_tasks = tasks.area_tasks(&area.name);
+            AreaJson {
+                name: area.name.clone(),
+                description: area.description.clone(),
+                archived: area.archived,
+                tags: area.tags.clone().unwrap_or_default(),
+                tasks: area_tasks.len(),
+                remaining: area_tasks.iter().filter(|task| !task.is_complete()).count(),
+            }
+        })
+        .collect();
+    emit(&areas);
+}
+
+pub fn tags(counts: &BTreeMap<String, usize>) {
+    let tags: Vec<TagJson> = counts
+        .iter()
+        .map(|(tag, count)| TagJson {
+            tag: tag.clone(),
+            count: *count,
+        })
+        .collect();
+    emit(&tags);
+}
+
+pub fn tag_change(change: TagChangeJson) {
+    emit(&[change]);
+}
+
+pub fn settings(settings: Vec<SettingJson>) {
+    emit(&settings);
+}
+
+pub fn profiles(profiles: Vec<ProfileJson>) {
+    emit(&profiles);
+}
+
+pub fn status(status: StatusJson) {
+    emit(&[status]);
+}
+
+pub fn sync(sync: SyncJson) {
+    emit(&[sync]);
+}
+
+pub fn undone(action: &'static str, messages: &[String]) {
+    let undone: Vec<UndoJson> = messages
+        .iter()
+        .map(|message| UndoJson {
+            action,
+            message: message.clone(),
+        })
+        .collect();
+    emit(&undone);
+}
+
+pub fn events(events: &[Event]) {
+    let events: Vec<EventJson> = events
+        .iter()
+        .map(|event| EventJson {
+            commit: event.commit.clone(),
+            date: iso_date(Some(event.date)).unwrap_or_default(),
+            message: event.subject.clone(),
+            uuid: event.uuid.hyphenated().to_string(),
+            id: event.id,
+            title: event.title.clone(),
+            kind: event.kind.as_str(),
+            changes: field_changes(&event.changes),
+            moved_from: event.moved_from.as_ref().map(|(_, path)| path.clone()),
+        })
+        .collect();
+    emit(&events);
+}
+
+pub fn diffs(diffs: &[TaskDiff]) {
+    let diffs: Vec<DiffJson> = diffs
+        .iter()
+        .map(|diff| DiffJson {
+            change: diff.kind.as_str(),
+            task: TaskJson::new(diff.tasks, diff.task),
+            changes: field_changes(&diff.changes),
+        })
+        .collect();
+    emit(&diffs);
+}
@@ -0,0 +1,130 @@
+use colored::Colorize;
+use uuid::Uuid;
+
+use crate::cli::dates::parse_since;
+use crate::cli::{json, output, tables};
+use crate::error::InertiaError;
+use crate::git::Repository;
+use crate::history::{self, Event};
+use crate::tasks::Tasks;
+
+/// Counts how many times each field of a task changed, such as "deadline changed 3 times"
+fn summary(events: &[Event]) -> Vec<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for change in events
+        .iter()
+        .filter(|event| event.kind == history::EventKind::Changed)
+        .flat_map(|event| &event.changes)
+    {
+        match counts.iter_mut().find(|(field, _)| *field == change.field) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((change.field, 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(field, count)| match count {
+            1 => format!("{} changed once", field),
+            _ => format!("{} changed {} times", field, count),
+        })
+        .collect()
+}
+
+pub fn log(tasks: &Tasks, id: Option<String>, since: Option<String>) -> Result<(), InertiaError> {
+    let repo = Repository::open(&tasks.path)?;
+    let since = since.as_deref().map(parse_since).transpose()?;
+
+    let uuid = match &id {
+        Some(id) => match tasks.position(id) {
+            Ok(index) => Some(tasks.tasks[index].uuid),
+            // Deleted tasks are only in the history, so they can only be found by their full uuid
+            Err(err) => Some(Uuid::parse_str(id).map_err(|_| err)?),
+        },
+        None => None,
+    };
+    let events = match uuid {
+        Some(uuid) => history::task_events(&repo, &tasks.file, since, uuid)?,
+        None => history::events(&repo, &tasks.file, "HEAD", since, None)?,
+    };
+
+    if output::is_json() {
+        json::events(&events);
+    } else if events.is_empty() {
+        output::info(String::from("no changes found"));
+    } else {
+        println!("{}", tables::log_table(&events, uuid.is_none()));
+
+        // Sum up how the task changed, which is hard to see from the whole timeline
+        let summary = summary(&events);
+        if uuid.is_some() && !summary.is_empty() {
+            output::info(summary.join(", ").blue().to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Finds the commit for a point in the history, given as a date or a commit, or None if it's from before the first commit
+fn commit_at(repo: &Repository, at: &str) -> Result<Option<String>, InertiaError> {
+    // Dates come first, as spans such as 100d or words such as "beef" could also be short commit ids
+    let Ok(date) = parse_since(at) else {
+        return match repo.resolve(at)? {
+            Some(commit) => Ok(Some(commit)),
+            None => Err(InertiaError::history(&format!(
+                "\"{}\" isn't a date or a commit",
+                at
+            ))),
+        };
+    };
+
+    // The tasks as they were then are the ones from the last commit made before it
+    Ok(repo
+        .log("HEAD", None)?
+        .into_iter()
+        .filter(|commit| commit.date <= date)
+        .max_by_key(|commit| commit.date)
+        .map(|commit| commit.id))
+}
+
+/// Loads the tasks as they were at a date or commit, or None if there weren't any yet
+fn load_at(repo: &Repository, tasks: &Tasks, at: &str) -> Result<Option<Tasks>, InertiaError> {
+    match commit_at(repo, at)? {
+        Some(commit) => history::tasks_at(repo, &tasks.file, &commit),
+        None => Ok(None),
+    }
+}
+
+/// Loads the tasks as they were at a date or commit, to be shown but never saved
+pub fn tasks_at(tasks: &Tasks, at: &str) -> Result<Tasks, InertiaError> {
+    let repo = Repository::open(&tasks.path)?;
+    load_at(&repo, tasks, at)?
+        .ok_or_else(|| InertiaError::history(&format!("there were no tasks yet at {}", at)))
+}
+
+pub fn diff(tasks: &Tasks, from: &str, to: Option<&str>) -> Result<(), InertiaError> {
+    let repo = Repository::open(&tasks.path)?;
+    let before = load_at(&repo, tasks, from)?;
+    let later = match to {
+        Some(to) => load_at(&repo, tasks, to)?,
+        None => None,
+    };
+
+    // Without an end point, compare with the tasks as they are now, including uncommitted changes
+    let after = if to.is_some() {
+        later.as_ref()
+    } else {
+        Some(tasks)
+    };
+    let diffs = history::diff(before.as_ref(), after);
+
+    if output::is_json() {
+        json::diffs(&diffs);
+    } else if diffs.is_empty() {
+        output::info(String::from(
+            "no tasks were added, removed, completed or rescheduled",
+        ));
+    } else {
+        println!("{}", tables::diff_table(&diffs));
+    }
+    Ok(())
+}
@@ -1,32 +1,64 @@
+use crate::args::OutputFormat;
 use crate::tasks::Task;
 use colored::Colorize;
+use std::sync::OnceLock;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+pub fn set_format(format: OutputFormat) {
+    FORMAT.get_or_init(|| format);
+}
+
+pub fn format() -> OutputFormat {
+    *FORMAT.get().unwrap_or(&OutputFormat::Table)
+}
+
+/// Checks if output should be JSON for other programs, rather than tables for people
+pub fn is_json() -> bool {
+    format() != OutputFormat::Table
+}
+
+/// Prints a message for the user, keeping it out of the way of any JSON
+pub fn message(msg: String) {
+    if is_json() {
+        eprintln!("{}", msg);
+    } else {
+        println!("{}", msg);
+    }
+}
 
 pub fn error(msg: String) {
-    println!("{} {}", "error".red().bold(), msg);
+    eprintln!("{} {}", "error:".red().bold(), msg);
 }
 
 pub fn warning(msg: String) {
-    println!("{} {}", "warning:".yellow().bold(), msg);
+    message(format!("{} {}", "warning:".yellow().bold(), msg));
 }
 
 pub fn info(msg: String) {
-    println!("{} {}", "info:".blue().bold(), msg);
-}
-
-pub fn git(msg: String) {
-    let msg = msg.strip_suffix('\n').unwrap_or(&msg);
-    println!("{} {}", "git:".blue().bold(), msg.bright_black().italic());
+    message(format!("{} {}", "info:".blue().bold(), msg));
 }
 
 pub fn success(msg: String) {
-    println!("{} {}", "success:".green().bold(), msg);
+    message(format!("{} {}", "success:".green().bold(), msg));
 }
 
-pub fn task_msg(msg: &str, task: &Task, id: usize) -> String {
+pub fn task_msg(msg: &str, task: &Task) -> String {
     format!(
         "{} task: {}({})",
         msg,
         task.title_string().blue(),
-        id.to_string().cyan()
+        task.id_string().cyan()
     )
 }
+
+pub fn commit_msg(cmd: &str, task: &Task) -> String {
+    format!("{}: \"{}\" ({})", cmd, task.title, task.id)
+}
+
+pub fn commits_msg(cmd: &str, tasks: &[Task]) -> String {
+    match tasks {
+        [task] => commit_msg(cmd, task),
+        tasks => format!("{}: {} tasks", cmd, tasks.len()),
+    }
+}
@@ -0,0 +1,175 @@
+use colored::Colorize;
+use std::env;
+use std::path::PathBuf;
+
+use crate::args::ProfileCommands;
+use crate::cli::bulk;
+use crate::cli::json::{self, ProfileJson};
+use crate::cli::output;
+use crate::cli::tables;
+use crate::config::{self, ProfileConfig, DEFAULT_PROFILE};
+use crate::error::InertiaError;
+use crate::git::Repository;
+use crate::history;
+use crate::repo::{self, Location};
+use crate::tasks::Tasks;
+
+pub fn execute(command: ProfileCommands) -> Result<(), InertiaError> {
+    match command {
+        ProfileCommands::Add(profile) => add(profile.name, profile.path, profile.remote),
+        ProfileCommands::List => list(),
+        ProfileCommands::Default(profile) => default(profile.name),
+    }
+}
+
+pub fn add(name: String, path: PathBuf, remote: Option<String>) -> Result<(), InertiaError> {
+    // Profile names are used on the command line and as keys in the config file
+    if name == DEFAULT_PROFILE {
+        return Err(InertiaError::invalid_profile(
+            &name,
+            "the name is used for the usual tasks repository",
+        ));
+    }
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(InertiaError::invalid_profile(
+            &name,
+            "names can only have letters, numbers, - and _",
+        ));
+    }
+
+    // Relative paths would change meaning with the working directory
+    let path = if path.is_relative() && !path.starts_with("~") {
+        env::current_dir()
+            .map_err(|err| InertiaError::io(&path, &err.to_string()))?
+            .join(path)
+    } else {
+        path
+    };
+
+    config::add_profile(&name, &ProfileConfig { path, remote })?;
+
+    // Success
+    output::success(format!("added profile: {}", name.blue()));
+    Ok(())
+}
+
+pub fn list() -> Result<(), InertiaError> {
+    config::load()?;
+    let config = config::get();
+
+    // The usual tasks repository is always there, as the default profile
+    let mut names = vec![DEFAULT_PROFILE];
+    names.extend(config.profiles.keys().map(String::as_str));
+    let current = config.profile_name(None);
+
+    let mut profiles = Vec::new();
+    for name in names {
+        profiles.push(ProfileJson {
+            name: name.to_string(),
+            path: repo::tasks_repo_path(None, Some(name))?,
+            remote: config.remote(Some(name))?.to_string(),
+            default: name == current,
+        });
+    }
+
+    if output::is_json() {
+        json::profiles(profiles);
+    } else {
+        // Print the table of profiles
+        println!("{}", tables::profiles_table(&profiles));
+    }
+
+    Ok(())
+}
+
+pub fn default(name: String) -> Result<(), InertiaError> {
+    if name == DEFAULT_PROFILE {
+        config::unset("repo.profile")?;
+    } else {
+        config::set_value("repo.profile", toml::Value::String(name.clone()))?;
+    }
+
+    // Success
+    output::success(format!("default profile is now {}", name.blue()));
+    Ok(())
+}
+
+/// Finds the repository of the profile tasks are being moved to, creating it if needed
+pub fn move_target(from: &Location, to: &str) -> Result<Location, InertiaError> {
+    let path = repo::tasks_repo_path(None, Some(to))?;
+    let target = Location::repo(path);
+    if target.is_same(from) {
+        return Err(InertiaError::invalid_profile(
+            to,
+            "the tasks are already in it",
+        ));
+    }
+
+    repo::ensure_repo(&target.path)?;
+    Ok(target)
+}
+
+pub fn move_tasks(
+    tasks: &mut Tasks,
+    id: String,
+    from: &str,
+    to: String,
+    yes: bool,
+) -> Result<String, InertiaError> {
+    // The other profile's tasks were locked along with these ones, before either were loaded
+    let target_location = Location::repo(repo::tasks_repo_path(None, Some(&to))?);
+    let selected = bulk::select(tasks, &id, "move", yes)?;
+    let mut target = repo::load_tasks(&target_location)?;
+
+    // Copy the tasks across, keeping their uuids so they can be followed between repositories
+    let mut moved = Vec::new();
+    for uuid in &selected {
+        let mut task = tasks.tasks[tasks.index_of(*uuid)?].clone();
+        if target.index_of(task.uuid).is_ok() {
+            return Err(InertiaError::invalid_profile(
+                &to,
+                &format!("it already has task {}", task.uuid),
+            ));
+        }
+
+        // Links can't reach tasks in another repository
+        task.links.clear();
+
+        // Bring the task's area along if the other profile doesn't have it
+        if let Some(area) = &task.area {
+            if target.area(area).is_err() {
+                target.push_area(tasks.area(area)?.clone())?;
+            }
+        }
+
+        moved.push(target.push(task).clone());
+    }
+
+    // Commit the tasks to the other profile first, so nothing is lost if it fails
+    let mut message = output::commits_msg(&format!("move from {}", from), &moved);
+
+    // Say where the tasks came from, so `inertia log` can follow their history back there
+    if !tasks.project {
+        if let Some(head) = Repository::open(&tasks.path)?.head()? {
+            let path = repo::canonical(&tasks.path);
+            message = format!("{}\n\n{}", message, history::moved_trailer(&head, &path));
+        }
+    }
+    repo::save_tasks(target_location.tasks_file(), &target)?;
+    repo::commit(&target_location.path, &message)?;
+
+    // Then remove them from this profile
+    let mut removed = Vec::new();
+    for uuid in selected {
+        removed.push(tasks.remove(&uuid.to_string())?);
+    }
+
+    // Success
+    bulk::report(&target, "moved", &moved, &[]);
+    output::info(format!("moved to profile {}", to.blue()));
+    Ok(output::commits_msg(&format!("move to {}", to), &removed))
+}
@@ -0,0 +1,30 @@
+use colored::Colorize;
+use std::env;
+use std::path::Path;
+
+use crate::cli::output;
+use crate::error::InertiaError;
+use crate::repo;
+use crate::tasks::Tasks;
+
+pub fn init(single_file: bool) -> Result<(), InertiaError> {
+    let dir =
+        env::current_dir().map_err(|err| InertiaError::io(Path::new("."), &err.to_string()))?;
+    let location = repo::init_project(&dir, single_file)?;
+
+    // Success
+    output::success(format!(
+        "created project tasks: {}",
+        location.tasks_file().display().to_string().blue()
+    ));
+    Ok(())
+}
+
+/// Checks the tasks have a repository of their own, as history commands would change the project's code too
+pub fn ensure_own_repo(tasks: &Tasks) -> Result<(), InertiaError> {
+    if tasks.project {
+        Err(InertiaError::project_history())
+    } else {
+        Ok(())
+    }
+}
@@ -1,67 +1,445 @@
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
 use prettytable::{format, row, Row, Table};
+use std::collections::BTreeMap;
 
+use crate::areas::Area;
+use crate::cli::json::{ProfileJson, StatusJson};
+use crate::config::{self, Column};
+use crate::history::{DiffKind, Event, EventKind, FieldChange, FieldValue, TaskDiff};
+use crate::next::Recommendation;
 use crate::tasks::{Task, Tasks};
 
-pub fn calc_row(task: &Task, id: usize) -> Row {
+fn column_title(column: Column) -> &'static str {
+    match column {
+        Column::Id => "ID",
+        Column::Status => "Status",
+        Column::Area => "Area",
+        Column::Tags => "Tags",
+        Column::Title => "Title",
+        Column::When => "When",
+        Column::Deadline => "Deadline",
+        Column::Reminder => "Reminder",
+        Column::Created => "Created",
+        Column::Repeats => "Repeats",
+    }
+}
+
+fn calc_cell(tasks: &Tasks, task: &Task, column: Column) -> ColoredString {
+    match column {
+        Column::Id => task.id_string().cyan(),
+        // Tasks waiting on others are marked as blocked
+        Column::Status if !task.is_complete() && tasks.is_blocked(task) => {
+            "⛔ Blocked".bright_red()
+        }
+        Column::Status => task.status_string(),
+        Column::Area => task.area_string(),
+        Column::Tags => task.tags_string(),
+        Column::Title => task.title_string(),
+        Column::When => task.when_string(),
+        Column::Deadline => task.deadline_string(),
+        Column::Reminder => task.reminder_string(),
+        Column::Created => task.created_string(),
+        Column::Repeats => task.recurrence_string(),
+    }
+}
+
+pub fn calc_row(tasks: &Tasks, task: &Task) -> Row {
+    // Only show the configured columns
+    let cells = config::get()
+        .display
+        .columns
+        .iter()
+        .map(|column| calc_cell(tasks, task, *column));
+
     if task.is_complete() {
         // Generate greyed out rows for complete tasks
-        Row::from([
-            id.to_string().bright_black().italic(),
-            task.status_string().bright_black().italic(),
-            task.tags_string().bright_black().italic(),
-            task.title_string().bright_black().italic(),
-            task.when_string().bright_black().italic(),
-            task.deadline_string().bright_black().italic(),
-        ])
+        Row::from(cells.map(|cell| cell.clear().bright_black().italic()))
     } else {
         // Generate normal colored rows for uncompleted tasks
-        Row::from([
-            id.to_string().cyan(),
-            task.status_string(),
-            task.tags_string(),
-            task.title_string(),
-            task.when_string(),
-            task.deadline_string(),
-        ])
+        Row::from(cells)
+    }
+}
+
+pub fn tasks_table(tasks: &Tasks, shown: &[&Task]) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(Row::from(
+        config::get()
+            .display
+            .columns
+            .iter()
+            .map(|column| column_title(*column).magenta().bold()),
+    ));
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each task
+    for task in shown {
+        table.add_row(calc_row(tasks, task));
     }
+
+    table
 }
 
-pub fn tasks_table(tasks: &Tasks) -> Table {
+pub fn next_table(tasks: &Tasks, recommendations: &[Recommendation]) -> Table {
     // Create the table for printing
     let mut table = Table::new();
     table.set_titles(row![
         "ID".magenta().bold(),
         "Status".magenta().bold(),
-        "Tags".magenta().bold(),
         "Title".magenta().bold(),
         "When".magenta().bold(),
         "Deadline".magenta().bold(),
+        "Why".magenta().bold(),
     ]);
     table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
 
-    // Iterate through each task
-    for (id, task) in tasks.tasks.iter().enumerate() {
-        table.add_row(calc_row(task, id));
+    // Iterate through each recommendation, best first
+    for recommendation in recommendations {
+        let task = &tasks.tasks[recommendation.index];
+        let reasons = if recommendation.reasons.is_empty() {
+            "no dates set".bright_black()
+        } else {
+            recommendation.reasons.join(", ").white()
+        };
+
+        table.add_row(Row::from([
+            task.id_string().cyan(),
+            task.status_string(),
+            task.title_string(),
+            task.when_string(),
+            task.deadline_string(),
+            reasons,
+        ]));
     }
 
     table
 }
 
-pub fn task_table(task: &Task, id: usize) -> Table {
+pub fn task_table(tasks: &Tasks, task: &Task) -> Table {
     let mut table = Table::new();
     table.set_titles(row!["Item".magenta().bold(), "Value".magenta().bold()]);
     table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
 
     // Add rows
-    table.add_row(row!["ID".white().bold(), id.to_string().cyan()]);
+    table.add_row(row!["ID".white().bold(), task.id_string().cyan()]);
+    table.add_row(row![
+        "UUID".white().bold(),
+        task.uuid_string().bright_black()
+    ]);
     table.add_row(row!["Status".white().bold(), task.status_string()]);
     table.add_row(row!["Title".white().bold(), task.title_string()]);
     table.add_row(row!["When".white().bold(), task.when_string(),]);
     table.add_row(row!["Deadline".white().bold(), task.deadline_string(),]);
     table.add_row(row!["Reminder".white().bold(), task.reminder_string(),]);
+    table.add_row(row!["Repeats".white().bold(), task.recurrence_string()]);
+    table.add_row(row!["Created".white().bold(), task.created_string(),]);
+    table.add_row(row!["Area".white().bold(), &task.area_string()]);
     table.add_row(row!["Tags".white().bold(), &task.tags_string()]);
     table.add_row(row!["Notes".white().bold(), &task.notes_string()]);
 
+    // Add a row for each linked task
+    for (kind, other) in tasks.links(task) {
+        let link = format!("{} {}({})", kind, other.title, other.id);
+        if other.is_complete() {
+            table.add_row(row!["Link".white().bold(), link.bright_black()]);
+        } else {
+            table.add_row(row!["Link".white().bold(), link.white()]);
+        }
+    }
+
+    table
+}
+
+pub fn areas_table(tasks: &Tasks, areas: &[&Area]) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(row![
+        "Name".magenta().bold(),
+        "Tasks".magenta().bold(),
+        "Tags".magenta().bold(),
+        "Description".magenta().bold(),
+    ]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each area
+    for area in areas {
+        // Count how many of the area's tasks are left to do
+        let area_tasks = tasks.area_tasks(&area.name);
+        let left = area_tasks.iter().filter(|task| !task.is_complete()).count();
+        let counts = format!("{}/{}", left, area_tasks.len());
+
+        let tags = match &area.tags {
+            Some(tags) => tags.join(", ").white(),
+            None => "N/A".bright_black(),
+        };
+        let description = match &area.description {
+            Some(description) => description.white(),
+            None => "N/A".bright_black(),
+        };
+
+        if area.archived {
+            // Grey out archived areas
+            table.add_row(Row::from([
+                area.name.bright_black().italic(),
+                counts.bright_black().italic(),
+                tags.bright_black().italic(),
+                description.bright_black().italic(),
+            ]));
+        } else {
+            table.add_row(Row::from([
+                area.name.cyan(),
+                counts.white(),
+                tags,
+                description,
+            ]));
+        }
+    }
+
+    table
+}
+
+pub fn tags_table(counts: &BTreeMap<String, usize>) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(row!["Tag".magenta().bold(), "Tasks".magenta().bold()]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each tag, indenting nested tags under their parents
+    for (tag, count) in counts {
+        let depth = tag.matches('/').count();
+        let name = tag.rsplit('/').next().unwrap_or(tag);
+        let name = format!("{}{}", "  ".repeat(depth), name);
+
+        table.add_row(Row::from([name.cyan(), count.to_string().white()]));
+    }
+
+    table
+}
+
+pub fn settings_table(settings: &[(&str, Option<toml::Value>, &str)]) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(row![
+        "Setting".magenta().bold(),
+        "Value".magenta().bold(),
+        "Description".magenta().bold(),
+    ]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each setting
+    for (key, value, description) in settings {
+        let value = match value {
+            Some(value) => value.to_string().white(),
+            None => "N/A".bright_black(),
+        };
+        table.add_row(Row::from([key.cyan(), value, description.white()]));
+    }
+
+    table
+}
+
+pub fn profiles_table(profiles: &[ProfileJson]) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(row![
+        "Name".magenta().bold(),
+        "Path".magenta().bold(),
+        "Remote".magenta().bold(),
+    ]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each profile, marking the default one
+    for profile in profiles {
+        let name = if profile.default {
+            format!("{} (default)", profile.name).green()
+        } else {
+            profile.name.cyan()
+        };
+        table.add_row(Row::from([
+            name,
+            profile.path.display().to_string().white(),
+            profile.remote.white(),
+        ]));
+    }
+
+    table
+}
+
+pub fn status_table(status: &StatusJson) -> Table {
+    let mut table = Table::new();
+    table.set_titles(row!["Item".magenta().bold(), "Value".magenta().bold()]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    let upstream = match &status.upstream {
+        Some(upstream) => upstream.white(),
+        None => "N/A".bright_black(),
+    };
+    let uncommitted = if status.uncommitted.is_empty() {
+        status.uncommitted.to_string().bright_black()
+    } else {
+        status.uncommitted.to_string().yellow()
+    };
+
+    // Add rows
+    table.add_row(row!["Profile".white().bold(), status.profile.cyan()]);
+    table.add_row(row![
+        "Path".white().bold(),
+        status.path.display().to_string().white()
+    ]);
+    table.add_row(row!["Branch".white().bold(), status.branch.white()]);
+    table.add_row(row!["Remote".white().bold(), upstream]);
+    table.add_row(row![
+        "Ahead".white().bold(),
+        status.ahead.to_string().white()
+    ]);
+    table.add_row(row![
+        "Behind".white().bold(),
+        status.behind.to_string().white()
+    ]);
+    table.add_row(row!["Uncommitted".white().bold(), uncommitted]);
+    table.add_row(row![
+        "Batched".white().bold(),
+        status.batched.to_string().white()
+    ]);
+
+    table
+}
+
+fn field_value_string(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Text(text) => text.clone(),
+        FieldValue::Date(date) => date.format(&config::get().display.date_format).to_string(),
+    }
+}
+
+/// Describes a change to one field, such as "deadline 2024-03-01 → 2024-03-08"
+fn field_change_string(change: &FieldChange) -> String {
+    // Notes can be long, so only say that they changed
+    if change.field == "notes" {
+        return match (&change.before, &change.after) {
+            (None, _) => String::from("notes added"),
+            (_, None) => String::from("notes removed"),
+            _ => String::from("notes changed"),
+        };
+    }
+
+    match (&change.before, &change.after) {
+        (None, Some(after)) => format!("{} set to {}", change.field, field_value_string(after)),
+        (Some(before), None) => format!(
+            "{} cleared, was {}",
+            change.field,
+            field_value_string(before)
+        ),
+        (Some(before), Some(after)) => format!(
+            "{} {} → {}",
+            change.field,
+            field_value_string(before),
+            field_value_string(after)
+        ),
+        (None, None) => change.field.to_string(),
+    }
+}
+
+/// Lists each change as a row, with a column for the task when showing changes to more than one
+pub fn log_table(events: &[Event], show_task: bool) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    let mut titles = vec!["Date".magenta().bold(), "Commit".magenta().bold()];
+    if show_task {
+        titles.push("Task".magenta().bold());
+    }
+    titles.push("Change".magenta().bold());
+    table.set_titles(Row::from(titles));
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each event, then each field that changed in it
+    for event in events {
+        let mut changes: Vec<ColoredString> = match event.kind {
+            EventKind::Created => vec!["created".green()],
+            EventKind::Deleted => vec!["deleted".red()],
+            EventKind::Moved => match &event.moved_from {
+                Some((_, path)) => vec![format!("moved from {}", path.display()).blue()],
+                None => vec!["moved".blue()],
+            },
+            EventKind::Changed => Vec::new(),
+        };
+        changes.extend(
+            event
+                .changes
+                .iter()
+                // New tasks always have a title and status, so only the other fields are worth showing
+                .filter(|change| {
+                    event.kind == EventKind::Changed || !matches!(change.field, "title" | "status")
+                })
+                .map(|change| match (change.field, &change.after) {
+                    ("status", Some(FieldValue::Text(status))) if status == "complete" => {
+                        field_change_string(change).green()
+                    }
+                    _ => field_change_string(change).white(),
+                }),
+        );
+
+        // Only the first row of an event says when and where it happened
+        for (index, change) in changes.into_iter().enumerate() {
+            let mut cells = if index == 0 {
+                vec![
+                    event
+                        .date
+                        .format(&config::get().display.date_format)
+                        .to_string()
+                        .white(),
+                    event.commit[..7].bright_black(),
+                ]
+            } else {
+                vec!["".normal(), "".normal()]
+            };
+            if show_task {
+                let task = format!("{}({})", event.title, event.id);
+                cells.push(if index == 0 { task.cyan() } else { "".normal() });
+            }
+            cells.push(change);
+            table.add_row(Row::from(cells));
+        }
+    }
+
+    table
+}
+
+pub fn diff_table(diffs: &[TaskDiff]) -> Table {
+    // Create the table for printing
+    let mut table = Table::new();
+    table.set_titles(row![
+        "Change".magenta().bold(),
+        "ID".magenta().bold(),
+        "Title".magenta().bold(),
+        "Details".magenta().bold(),
+    ]);
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    // Iterate through each task that changed
+    for diff in diffs {
+        let change = match diff.kind {
+            DiffKind::Added => diff.kind.as_str().green(),
+            DiffKind::Removed => diff.kind.as_str().red(),
+            DiffKind::Completed => diff.kind.as_str().green(),
+            DiffKind::Reopened => diff.kind.as_str().yellow(),
+            DiffKind::Rescheduled => diff.kind.as_str().blue(),
+        };
+        let details: Vec<String> = diff.changes.iter().map(field_change_string).collect();
+
+        // Removed tasks' ids may have been given to other tasks since, so grey them out
+        let id = match diff.kind {
+            DiffKind::Removed => diff.task.id_string().bright_black(),
+            _ => diff.task.id_string().cyan(),
+        };
+
+        table.add_row(Row::from([
+            change,
+            id,
+            diff.task.title_string(),
+            details.join(", ").white(),
+        ]));
+    }
+
     table
 }
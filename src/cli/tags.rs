@@ -0,0 +1,123 @@
+use colored::Colorize;
+use std::collections::HashMap;
+
+use crate::cli::json::{self, TagChangeJson};
+use crate::cli::output;
+use crate::cli::tables;
+use crate::config;
+use crate::error::InertiaError;
+use crate::tags;
+use crate::tasks::Tasks;
+
+fn parse_tag(tag: &str) -> Result<String, InertiaError> {
+    tags::normalise(tag).ok_or_else(|| InertiaError::no_tag(tag))
+}
+
+/// Runs a change to the tags, saving the scores `next` gives tags if it moved any of them
+fn change_weights(
+    change: impl FnOnce(&mut HashMap<String, f64>) -> Result<usize, InertiaError>,
+) -> Result<usize, InertiaError> {
+    let mut weights = config::get().next.tags.clone();
+    let changed = change(&mut weights)?;
+
+    if weights != config::get().next.tags {
+        let weights = weights
+            .into_iter()
+            .map(|(tag, weight)| (tag, toml::Value::from(weight)))
+            .collect();
+        config::set_value("next.tags", toml::Value::Table(weights))?;
+    }
+    Ok(changed)
+}
+
+pub fn list(tasks: &Tasks) {
+    let counts = tasks.tag_counts();
+
+    if output::is_json() {
+        json::tags(&counts);
+    } else if counts.is_empty() {
+        // Output when no tags are in use
+        output::info(String::from("no tags found"))
+    } else {
+        // Generate the table of tags
+        let table = tables::tags_table(&counts);
+        // Print the table
+        println!("{}", table);
+    }
+}
+
+pub fn rename(tasks: &mut Tasks, tag: String, new_tag: String) -> Result<String, InertiaError> {
+    // Rename the tag on every task
+    let (tag, new_tag) = (parse_tag(&tag)?, parse_tag(&new_tag)?);
+    let changed = change_weights(|weights| tasks.rename_tag(&tag, &new_tag, weights))?;
+
+    if output::is_json() {
+        json::tag_change(TagChangeJson {
+            tags: vec![tag.clone()],
+            into: Some(new_tag.clone()),
+            tasks: changed,
+        });
+    }
+
+    // Success
+    output::success(format!(
+        "renamed tag: {} to {} on {} tasks",
+        tag.blue(),
+        new_tag.blue(),
+        changed
+    ));
+    Ok(format!("tag rename: \"{}\" -> \"{}\"", tag, new_tag))
+}
+
+pub fn merge(tasks: &mut Tasks, tags: Vec<String>, into: String) -> Result<String, InertiaError> {
+    // Merge the tags on every task
+    let tags = tags
+        .iter()
+        .map(|tag| parse_tag(tag))
+        .collect::<Result<Vec<String>, InertiaError>>()?;
+    let into = parse_tag(&into)?;
+    let changed = change_weights(|weights| tasks.merge_tags(&tags, &into, weights))?;
+
+    if output::is_json() {
+        json::tag_change(TagChangeJson {
+            tags: tags.clone(),
+            into: Some(into.clone()),
+            tasks: changed,
+        });
+    }
+
+    // Success
+    output::success(format!(
+        "merged tags: {} into {} on {} tasks",
+        tags.join(", ").blue(),
+        into.blue(),
+        changed
+    ));
+    Ok(format!(
+        "tag merge: \"{}\" -> \"{}\"",
+        tags.join("\", \""),
+        into
+    ))
+}
+
+pub fn delete(tasks: &mut Tasks, tag: String) -> Result<String, InertiaError> {
+    // Remove the tag from every task
+    let tag = parse_tag(&tag)?;
+    let changed = change_weights(|weights| tasks.delete_tag(&tag, weights))?;
+
+    if output::is_json() {
+        json::tag_change(TagChangeJson {
+            tags: vec![tag.clone()],
+            into: None,
+            tasks: changed,
+        });
+    }
+
+    // Success
+    output::success(format!(
+        "deleted tag: {} from {} tasks",
+        tag.blue(),
+        changed
+    ));
+    Ok(format!("tag delete: \"{}\"", tag))
+}
@@ -0,0 +1,372 @@
+use chrono::format::{Item, StrftimeItems};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::error::InertiaError;
+use crate::next::Weights;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// The profile for the tasks repository that isn't in a named profile
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Every setting in the config file, with what it's for
+pub const KEYS: &[(&str, &str)] = &[
+    ("repo.path", "Directory of the tasks repository"),
+    ("repo.file", "Name of the tasks file in the repository"),
+    ("repo.remote", "Git remote to sync with"),
+    ("repo.profile", "Profile used when --profile isn't given"),
+    (
+        "repo.lock_timeout",
+        "Seconds to wait for another inertia to finish",
+    ),
+    ("display.columns", "Columns shown in tables of tasks"),
+    (
+        "display.date_format",
+        "How dates are shown, such as \"%d/%m/%Y\"",
+    ),
+    ("display.colors", "Whether to show colours"),
+    ("defaults.area", "Area new tasks are put in"),
+    ("defaults.tags", "Tags given to new tasks"),
+    (
+        "defaults.when",
+        "When date given to new tasks, such as \"today\"",
+    ),
+    ("next.active", "Score for tasks that have been started"),
+    (
+        "next.scheduled",
+        "Score for tasks with a when date of today or earlier",
+    ),
+    ("next.deadline", "Score for tasks with a deadline coming up"),
+    (
+        "next.deadline_days",
+        "Days ahead a deadline starts to count",
+    ),
+    (
+        "next.overdue",
+        "Score for overdue tasks, on top of next.deadline",
+    ),
+    ("next.age", "Score for every day since a task was created"),
+    ("next.inbox", "Score for tasks without a when date"),
+    (
+        "next.tags",
+        "Scores for tags, such as \"{ urgent = 20.0 }\"",
+    ),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Id,
+    Status,
+    Area,
+    Tags,
+    Title,
+    When,
+    Deadline,
+    Reminder,
+    Created,
+    Repeats,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub path: Option<PathBuf>, // Directory of the tasks repository, if not the default
+    pub file: String,          // Name of the tasks file in the repository
+    pub remote: String,        // Git remote to sync with
+    pub profile: Option<String>, // Profile used when --profile isn't given
+    pub lock_timeout: u64,     // Seconds to wait for another inertia to finish with the tasks
+}
+
+impl Default for RepoConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            file: String::from("tasks.toml"),
+            remote: String::from("origin"),
+            profile: None,
+            lock_timeout: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub columns: Vec<Column>, // Columns shown in tables of tasks, in order
+    pub date_format: String,  // How dates are shown, in strftime format
+    pub colors: bool,         // Whether to show colours
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                Column::Id,
+                Column::Status,
+                Column::Area,
+                Column::Tags,
+                Column::Title,
+                Column::When,
+                Column::Deadline,
+            ],
+            date_format: String::from("%Y-%m-%d"),
+            colors: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultsConfig {
+    pub area: Option<String>, // Area new tasks are put in
+    pub tags: Vec<String>,    // Tags given to new tasks
+    pub when: Option<String>, // When date given to new tasks, such as "today"
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub path: PathBuf, // Directory of the profile's tasks repository
+    #[serde(default)]
+    pub remote: Option<String>, // Git remote to sync with, if not repo.remote
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub repo: RepoConfig,                          // Where tasks are kept
+    pub display: DisplayConfig,                    // How tasks are shown
+    pub defaults: DefaultsConfig,                  // What new tasks start with
+    pub next: Weights, // How tasks are scored when deciding what to do next
+    pub profiles: BTreeMap<String, ProfileConfig>, // Named task repositories, such as work and personal
+}
+
+impl Config {
+    /// Returns the name of the profile in use, from --profile or repo.profile
+    pub fn profile_name<'a>(&'a self, profile: Option<&'a str>) -> &'a str {
+        profile
+            .or(self.repo.profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Finds the profile in use, or None for the default tasks repository
+    pub fn profile(&self, profile: Option<&str>) -> Result<Option<&ProfileConfig>, InertiaError> {
+        match self.profile_name(profile) {
+            DEFAULT_PROFILE => Ok(None),
+            name => self
+                .profiles
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| InertiaError::no_profile(name)),
+        }
+    }
+
+    /// Returns the git remote to sync a profile with
+    pub fn remote(&self, profile: Option<&str>) -> Result<&str, InertiaError> {
+        Ok(self
+            .profile(profile)?
+            .and_then(|profile| profile.remote.as_deref())
+            .unwrap_or(&self.repo.remote))
+    }
+
+    /// Checks settings that can be the right type but still not make sense
+    fn validate(&self) -> Result<(), InertiaError> {
+        if self.repo.file.trim().is_empty() {
+            return Err(InertiaError::invalid_setting(
+                "repo.file",
+                "it can't be empty",
+            ));
+        }
+        if let Some(profile) = &self.repo.profile {
+            self.profile(Some(profile))
+                .map_err(|err| InertiaError::invalid_setting("repo.profile", &err.to_string()))?;
+        }
+        if self.display.columns.is_empty() {
+            return Err(InertiaError::invalid_setting(
+                "display.columns",
+                "at least one column is needed",
+            ));
+        }
+        if StrftimeItems::new(&self.display.date_format).any(|item| item == Item::Error) {
+            return Err(InertiaError::invalid_setting(
+                "display.date_format",
+                "it isn't a valid date format",
+            ));
+        }
+        Ok(())
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Finds the config file, following the XDG base directory spec
+pub fn path() -> Result<PathBuf, InertiaError> {
+    // Relative paths in XDG variables should be ignored
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+    let config_home = match config_home {
+        Some(config_home) => config_home,
+        None => home_dir()
+            .ok_or_else(InertiaError::no_home)?
+            .join(".config"),
+    };
+    Ok(config_home.join("inertia").join(CONFIG_FILE))
+}
+
+/// Reads the config file as a table, which only holds the settings that have been changed
+fn read_table(path: &Path) -> Result<toml::Table, InertiaError> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let data = fs::read_to_string(path).map_err(|err| InertiaError::io(path, &err.to_string()))?;
+    toml::from_str(&data).map_err(|err| InertiaError::toml(path, &err.to_string()))
+}
+
+fn from_table(path: &Path, table: toml::Table) -> Result<Config, InertiaError> {
+    let config: Config = toml::Value::Table(table)
+        .try_into()
+        .map_err(|err: toml::de::Error| InertiaError::toml(path, &err.to_string()))?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Loads the config file, so the settings can be used anywhere with `get`
+pub fn load() -> Result<(), InertiaError> {
+    let path = path()?;
+    let config = from_table(&path, read_table(&path)?)?;
+    CONFIG.get_or_init(|| config);
+    Ok(())
+}
+
+/// Returns the settings, which are the defaults until the config file is loaded
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Splits a key such as "display.columns" into its section and name
+fn split_key(key: &str) -> Result<(&str, &str), InertiaError> {
+    match key.split_once('.') {
+        Some(split) if KEYS.iter().any(|(known, _)| *known == key) => Ok(split),
+        _ => Err(InertiaError::unknown_setting(key)),
+    }
+}
+
+/// Returns the value of a setting, or None if it isn't set
+pub fn value(config: &Config, key: &str) -> Result<Option<toml::Value>, InertiaError> {
+    let (section, name) = split_key(key)?;
+    let table = toml::Table::try_from(config)
+        .map_err(|err| InertiaError::invalid_setting(key, &err.to_string()))?;
+    Ok(table
+        .get(section)
+        .and_then(|section| section.get(name))
+        .cloned())
+}
+
+/// Reads a value as TOML, falling back to a string, or a list of strings for list settings
+fn parse_value(key: &str, value: &str) -> Result<toml::Value, InertiaError> {
+    if let Ok(mut table) = toml::from_str::<toml::Table>(&format!("value = {}", value)) {
+        if let Some(value) = table.remove("value") {
+            return Ok(value);
+        }
+    }
+
+    let is_list = matches!(value_of_default(key)?, Some(toml::Value::Array(_)));
+    if is_list {
+        Ok(toml::Value::Array(
+            value
+                .split(',')
+                .map(|item| toml::Value::String(item.trim().to_string()))
+                .collect(),
+        ))
+    } else {
+        Ok(toml::Value::String(value.to_string()))
+    }
+}
+
+fn value_of_default(key: &str) -> Result<Option<toml::Value>, InertiaError> {
+    value(&Config::default(), key)
+}
+
+fn write_table(path: &Path, key: &str, table: toml::Table) -> Result<(), InertiaError> {
+    // Make sure the settings still make sense before saving them
+    let config: Config = toml::Value::Table(table.clone())
+        .try_into()
+        .map_err(|err: toml::de::Error| InertiaError::invalid_setting(key, err.message()))?;
+    config.validate()?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| InertiaError::io(dir, &err.to_string()))?;
+    }
+    let data =
+        toml::to_string_pretty(&table).map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+    fs::write(path, data).map_err(|err| InertiaError::io(path, &err.to_string()))
+}
+
+/// Changes a setting in the config file
+pub fn set(key: &str, value: &str) -> Result<toml::Value, InertiaError> {
+    let value = parse_value(key, value)?;
+    set_value(key, value.clone())?;
+    Ok(value)
+}
+
+/// Changes a setting in the config file to a value that has already been parsed
+pub fn set_value(key: &str, value: toml::Value) -> Result<(), InertiaError> {
+    let (section, name) = split_key(key)?;
+    insert(section, name, key, value)
+}
+
+/// Adds a profile to the config file, replacing any profile with the same name
+pub fn add_profile(name: &str, profile: &ProfileConfig) -> Result<(), InertiaError> {
+    let key = format!("profiles.{}", name);
+    let value = toml::Value::try_from(profile)
+        .map_err(|err| InertiaError::invalid_setting(&key, &err.to_string()))?;
+    insert("profiles", name, &key, value)
+}
+
+fn insert(section: &str, name: &str, key: &str, value: toml::Value) -> Result<(), InertiaError> {
+    let path = path()?;
+    let mut table = read_table(&path)?;
+    let section = table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    match section.as_table_mut() {
+        Some(section) => section.insert(name.to_string(), value),
+        None => {
+            return Err(InertiaError::invalid_setting(
+                key,
+                "its section isn't a table",
+            ))
+        }
+    };
+
+    write_table(&path, key, table)
+}
+
+/// Removes a setting from the config file, so it goes back to its default
+pub fn unset(key: &str) -> Result<(), InertiaError> {
+    let (section, name) = split_key(key)?;
+
+    let path = path()?;
+    let mut table = read_table(&path)?;
+    if let Some(settings) = table
+        .get_mut(section)
+        .and_then(|section| section.as_table_mut())
+    {
+        settings.remove(name);
+
+        // Don't leave empty sections behind
+        if settings.is_empty() {
+            table.remove(section);
+        }
+    }
+
+    write_table(&path, key, table)
+}
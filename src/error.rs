@@ -0,0 +1,244 @@
+use std::fmt;
+use std::path::Path;
+
+/// Everything that can go wrong, each kind exiting with its own code so scripts can tell them apart:
+///
+/// | Code | Error                                          |
+/// |------|------------------------------------------------|
+/// | 0    | Success                                        |
+/// | 1    | Invalid input, such as an unknown filter       |
+/// | 2    | Invalid command line arguments                 |
+/// | 3    | A task, area, tag or profile couldn't be found |
+/// | 4    | A date couldn't be understood                  |
+/// | 5    | A file couldn't be read or written             |
+/// | 6    | The tasks or config file isn't valid           |
+/// | 7    | A git command failed                           |
+/// | 8    | The editor couldn't be run                     |
+/// | 9    | A change wasn't confirmed                      |
+/// | 10   | The tasks are locked by another inertia        |
+/// | 11   | The tasks file needs a newer inertia           |
+#[derive(Debug)]
+pub enum InertiaError {
+    Validation(String), // The input doesn't make sense
+    NotFound(String),   // A task, area, tag or profile couldn't be found
+    Date(String),       // A date couldn't be understood
+    Io(String),         // A file couldn't be read or written
+    Toml(String),       // The tasks couldn't be read from or written to TOML
+    Git(String),        // A git command failed
+    Editor(String),     // The editor couldn't be run, or the edit couldn't be used
+    Cancelled(String),  // The user didn't confirm a change
+    Locked(String),     // Another inertia is changing the tasks
+    Version(String),    // The tasks file was written by a newer inertia
+}
+
+impl InertiaError {
+    /// The code the process exits with for this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            InertiaError::Validation(_) => 1,
+            InertiaError::NotFound(_) => 3,
+            InertiaError::Date(_) => 4,
+            InertiaError::Io(_) => 5,
+            InertiaError::Toml(_) => 6,
+            InertiaError::Git(_) => 7,
+            InertiaError::Editor(_) => 8,
+            InertiaError::Cancelled(_) => 9,
+            InertiaError::Locked(_) => 10,
+            InertiaError::Version(_) => 11,
+        }
+    }
+}
+
+impl InertiaError {
+    pub fn no_task(id: &str) -> Self {
+        Self::NotFound(format!("couldn't find task with id {}", id))
+    }
+
+    pub fn ambiguous_task(id: &str) -> Self {
+        Self::NotFound(format!("task id {} matches more than one task", id))
+    }
+
+    pub fn no_tasks() -> Self {
+        Self::NotFound(String::from("no tasks available"))
+    }
+
+    pub fn no_matches(filter: &str) -> Self {
+        Self::NotFound(format!("no tasks match {}", filter))
+    }
+
+    pub fn no_area(name: &str) -> Self {
+        Self::NotFound(format!("couldn't find area {}", name))
+    }
+
+    pub fn no_tag(tag: &str) -> Self {
+        Self::NotFound(format!("couldn't find tag {}", tag))
+    }
+
+    pub fn no_profile(name: &str) -> Self {
+        Self::NotFound(format!(
+            "couldn't find profile {}, add it with inertia profile add",
+            name
+        ))
+    }
+
+    pub fn area_exists(name: &str) -> Self {
+        Self::Validation(format!("area {} already exists", name))
+    }
+
+    pub fn area_archived(name: &str) -> Self {
+        Self::Validation(format!("area {} is archived", name))
+    }
+
+    pub fn tag_exists(tag: &str) -> Self {
+        Self::Validation(format!("tag {} already exists, merge into it instead", tag))
+    }
+
+    pub fn misplaced_option(option: &str) -> Self {
+        Self::Validation(format!(
+            "options such as {} must come before the title, tag changes or filter",
+            option
+        ))
+    }
+
+    pub fn invalid_link(reason: &str) -> Self {
+        Self::Validation(format!("couldn't link tasks: {}", reason))
+    }
+
+    pub fn invalid_recurrence(rule: &str) -> Self {
+        Self::Validation(format!(
+            "couldn't understand repeat rule \"{}\", try \"weekly\", \"every 2 months\", \"every mon,thu\" or \"3 days after completion\"",
+            rule
+        ))
+    }
+
+    pub fn invalid_filter(term: &str, reason: &str) -> Self {
+        Self::Validation(format!("couldn't understand filter {}: {}", term, reason))
+    }
+
+    pub fn reversed_range(start: usize, end: usize) -> Self {
+        Self::Validation(format!(
+            "the range {}-{} goes backwards, try {}-{}",
+            start, end, end, start
+        ))
+    }
+
+    pub fn rename_many() -> Self {
+        Self::Validation(String::from(
+            "several tasks can't be given the same title at once",
+        ))
+    }
+
+    pub fn unknown_setting(key: &str) -> Self {
+        Self::Validation(format!("unknown setting {}, see inertia config list", key))
+    }
+
+    pub fn invalid_setting(key: &str, reason: &str) -> Self {
+        Self::Validation(format!("invalid setting {}: {}", key, reason))
+    }
+
+    pub fn invalid_profile(name: &str, reason: &str) -> Self {
+        Self::Validation(format!("invalid profile {}: {}", name, reason))
+    }
+
+    pub fn project_exists(file: &Path) -> Self {
+        Self::Validation(format!("{} already exists", file.display()))
+    }
+
+    pub fn project_history() -> Self {
+        Self::Validation(String::from(
+            "project tasks are committed along with the project's code, use git in the project instead or --global",
+        ))
+    }
+
+    pub fn history(reason: &str) -> Self {
+        Self::Validation(reason.to_string())
+    }
+
+    pub fn invalid_date(date: &str) -> Self {
+        Self::Date(format!("couldn't understand date \"{}\"", date))
+    }
+
+    pub fn io(path: &Path, reason: &str) -> Self {
+        Self::Io(format!("couldn't access {}: {}", path.display(), reason))
+    }
+
+    pub fn toml(path: &Path, reason: &str) -> Self {
+        Self::Toml(format!("{} isn't valid: {}", path.display(), reason))
+    }
+
+    pub fn newer_file(path: &Path, version: u32, supported: u32) -> Self {
+        Self::Version(format!(
+            "{} is version {}, but this inertia only reads up to version {}, upgrade inertia to use it",
+            path.display(),
+            version,
+            supported
+        ))
+    }
+
+    pub fn no_home() -> Self {
+        Self::Io(String::from("couldn't find your home directory"))
+    }
+
+    pub fn git(reason: &str) -> Self {
+        Self::Git(format!("git: {}", reason))
+    }
+
+    pub fn merge_failed(branch: &str, files: &[String]) -> Self {
+        Self::Git(format!(
+            "couldn't merge {} since {} changed in ways that can't be merged, nothing was changed",
+            branch,
+            files.join(", ")
+        ))
+    }
+
+    pub fn unchanged(state: &str) -> Self {
+        Self::Validation(format!(
+            "the tasks are already {}, nothing was changed",
+            state
+        ))
+    }
+
+    pub fn edit_failed(reason: &str) -> Self {
+        Self::Editor(format!("couldn't edit task: {}", reason))
+    }
+
+    pub fn cancelled() -> Self {
+        Self::Cancelled(String::from("cancelled, no tasks were changed"))
+    }
+
+    pub fn unconfirmed(count: usize) -> Self {
+        Self::Cancelled(format!(
+            "changing {} tasks needs confirmation, use --yes to change them anyway",
+            count
+        ))
+    }
+
+    pub fn locked(lock: &Path, pid: Option<u32>) -> Self {
+        let owner = match pid {
+            Some(pid) => format!("pid {}", pid),
+            None => String::from("another process"),
+        };
+        Self::Locked(format!(
+            "the tasks are locked by {}, try again or remove {} if it isn't running",
+            owner,
+            lock.display()
+        ))
+    }
+}
+
+impl fmt::Display for InertiaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InertiaError::Validation(message)
+            | InertiaError::NotFound(message)
+            | InertiaError::Date(message)
+            | InertiaError::Io(message)
+            | InertiaError::Toml(message)
+            | InertiaError::Git(message)
+            | InertiaError::Editor(message)
+            | InertiaError::Cancelled(message)
+            | InertiaError::Locked(message)
+            | InertiaError::Version(message) => write!(f, "{}", message),
+        }
+    }
+}
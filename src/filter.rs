@@ -0,0 +1,394 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::tags;
+use crate::tasks::{Status, Task, Tasks, MIN_NUMERIC_PREFIX};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DateMatch {
+    On(NaiveDate),     // The date is on this day
+    Before(NaiveDate), // The date is before this day
+    After(NaiveDate),  // The date is after this day
+    Unset,             // There is no date
+}
+
+impl DateMatch {
+    fn matches(&self, date: &Option<NaiveDateTime>) -> bool {
+        let date = date.map(|date| date.date());
+        match (self, date) {
+            (DateMatch::On(day), Some(date)) => date == *day,
+            (DateMatch::Before(day), Some(date)) => date < *day,
+            (DateMatch::After(day), Some(date)) => date > *day,
+            (DateMatch::Unset, None) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Status(Status),       // status:pending
+    Tag(Option<String>),  // tag:work, including nested tags, or tag:none
+    Area(Option<String>), // area:home or area:none
+    Due(DateMatch),       // due:friday, due.before:friday, due.after:friday or due:none
+    When(DateMatch),      // when:today, when.before:today, when.after:today or when:none
+    Title(String),        // title~email, or just email
+}
+
+impl Term {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Term::Status(status) => task.status == *status,
+            Term::Tag(Some(tag)) => task.has_tag(tag),
+            Term::Tag(None) => task.tags.is_none(),
+            Term::Area(area) => task.area == *area,
+            Term::Due(date) => date.matches(&task.deadline),
+            Term::When(date) => date.matches(&task.when),
+            Term::Title(text) => task.title.to_lowercase().contains(text),
+        }
+    }
+}
+
+/// Tasks that match every term of an expression such as `status:pending tag:work -tag:someday`
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    terms: Vec<(bool, Term)>, // Each term, and whether it has been negated with -
+}
+
+/// Splits an expression into terms at spaces, keeping "quoted text" together
+fn split(expression: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    terms
+}
+
+fn parse_status(value: &str) -> Option<Status> {
+    match value {
+        "inbox" => Some(Status::Inbox),
+        "pending" => Some(Status::Pending),
+        "active" => Some(Status::Active),
+        "complete" | "completed" | "done" => Some(Status::Complete),
+        _ => None,
+    }
+}
+
+fn parse_date(term: &str, field: &str, value: &str) -> Result<DateMatch, InertiaError> {
+    if value == "none" && !field.contains('.') {
+        return Ok(DateMatch::Unset);
+    }
+
+    let date = fuzzydate::parse(value)
+        .map_err(|_| InertiaError::invalid_date(value))?
+        .date();
+    match field.split_once('.').map(|(_, when)| when) {
+        None => Ok(DateMatch::On(date)),
+        Some("before") => Ok(DateMatch::Before(date)),
+        Some("after") => Ok(DateMatch::After(date)),
+        Some(_) => Err(InertiaError::invalid_filter(
+            term,
+            "dates can be .before or .after",
+        )),
+    }
+}
+
+fn parse_term(term: &str) -> Result<Term, InertiaError> {
+    // Words without a field match against the title
+    let Some(split) = term.find([':', '~']) else {
+        return Ok(Term::Title(term.to_lowercase()));
+    };
+    let (field, value) = (&term[..split], &term[split + 1..]);
+    if value.is_empty() {
+        return Err(InertiaError::invalid_filter(
+            term,
+            "there is no value to match",
+        ));
+    }
+
+    let lowercase = value.to_lowercase();
+    match field {
+        "title" => Ok(Term::Title(lowercase)),
+        _ if term[split..].starts_with('~') => Err(InertiaError::invalid_filter(
+            term,
+            "only the title can be searched with ~",
+        )),
+        "status" => parse_status(&lowercase)
+            .map(Term::Status)
+            .ok_or_else(|| InertiaError::invalid_filter(term, "unknown status")),
+        "tag" if lowercase == "none" => Ok(Term::Tag(None)),
+        "tag" => tags::normalise(value)
+            .map(|tag| Term::Tag(Some(tag)))
+            .ok_or_else(|| InertiaError::invalid_filter(term, "the tag is empty")),
+        "area" if lowercase == "none" => Ok(Term::Area(None)),
+        "area" => Ok(Term::Area(Some(value.to_string()))),
+        "due" | "due.before" | "due.after" | "deadline" | "deadline.before" | "deadline.after" => {
+            Ok(Term::Due(parse_date(term, field, &lowercase)?))
+        }
+        "when" | "when.before" | "when.after" => {
+            Ok(Term::When(parse_date(term, field, &lowercase)?))
+        }
+        _ => Err(InertiaError::invalid_filter(term, "unknown field")),
+    }
+}
+
+impl Filter {
+    /// Parses a filter from command line arguments, each of which may hold several terms
+    pub fn parse(args: &[String]) -> Result<Self, InertiaError> {
+        let mut terms = Vec::new();
+        for term in args.iter().flat_map(|arg| split(arg)) {
+            // Options after the filter would otherwise be taken as part of it
+            if term.starts_with("--") || (term.starts_with('-') && term.chars().count() == 2) {
+                return Err(InertiaError::misplaced_option(&term));
+            }
+
+            // A leading - negates a term, so -tag:someday hides tasks tagged someday
+            let (negated, rest) = match term.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, term.as_str()),
+            };
+            terms.push((negated, parse_term(rest)?));
+        }
+        Ok(Self { terms })
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.terms
+            .iter()
+            .all(|(negated, term)| term.matches(task) != *negated)
+    }
+
+    /// Checks if the filter asks for tasks in a particular area
+    pub fn has_area(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|(negated, term)| !negated && matches!(term, Term::Area(Some(_))))
+    }
+}
+
+impl Tasks {
+    /// Returns the tasks matching a filter
+    pub fn filter(&self, filter: &Filter) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| filter.matches(task))
+            .collect()
+    }
+
+    /// Checks if an argument refers to a task by its id, a range of ids or a uuid, rather than being a filter
+    pub fn is_key(&self, arg: &str) -> bool {
+        if arg.is_empty()
+            || arg.starts_with('-')
+            || !arg.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        {
+            return false;
+        }
+
+        // Words such as "cafe" are searched for in titles, unless they're long enough or start a uuid
+        let numbers = arg.split('-').all(|part| part.parse::<usize>().is_ok());
+        let prefix = arg.to_lowercase();
+        numbers
+            || arg.len() >= MIN_NUMERIC_PREFIX
+            || self
+                .tasks
+                .iter()
+                .any(|task| task.uuid_string().starts_with(&prefix))
+    }
+
+    /// Returns the uuids of the tasks with ids in a range, such as 3-9, or None if it isn't one
+    fn range(&self, key: &str) -> Result<Option<Vec<Uuid>>, InertiaError> {
+        let Some((start, end)) = key.split_once('-') else {
+            return Ok(None);
+        };
+        let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) else {
+            return Ok(None);
+        };
+
+        // Uuid prefixes such as 12345678-1234 look like ranges too, so both ends have to be tasks
+        let exists = |id: usize| self.tasks.iter().any(|task| task.id == id);
+        if !exists(start) || !exists(end) {
+            return Ok(None);
+        }
+        if start > end {
+            return Err(InertiaError::reversed_range(start, end));
+        }
+
+        let selected = self
+            .tasks
+            .iter()
+            .filter(|task| (start..=end).contains(&task.id))
+            .map(|task| task.uuid)
+            .collect();
+        Ok(Some(selected))
+    }
+
+    /// Finds the uuids of the tasks an id, list of ids (1,4,7), range of ids (3-9) or filter refers to
+    pub fn select(&self, selector: &str) -> Result<Vec<Uuid>, InertiaError> {
+        if selector.split(',').all(|key| self.is_key(key)) {
+            // Every task has to exist, so nothing is changed if any of them are mistyped
+            let mut selected = Vec::new();
+            for key in selector.split(',') {
+                let uuids = match self.range(key)? {
+                    Some(range) => range,
+                    None => vec![self.tasks[self.position(key)?].uuid],
+                };
+                for uuid in uuids {
+                    if !selected.contains(&uuid) {
+                        selected.push(uuid);
+                    }
+                }
+            }
+            return Ok(selected);
+        }
+
+        let filter = Filter::parse(&[selector.to_string()])?;
+        let selected: Vec<Uuid> = self.filter(&filter).iter().map(|task| task.uuid).collect();
+        if selected.is_empty() {
+            Err(InertiaError::no_matches(selector))
+        } else {
+            Ok(selected)
+        }
+    }
+
+    /// Finds the index of a task from its uuid
+    pub fn index_of(&self, uuid: Uuid) -> Result<usize, InertiaError> {
+        self.tasks
+            .iter()
+            .position(|task| task.uuid == uuid)
+            .ok_or_else(|| InertiaError::no_task(&uuid.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::Location;
+    use std::path::PathBuf;
+
+    fn tasks(titles: &[&str]) -> Tasks {
+        let location = Location {
+            path: PathBuf::from("tasks"),
+            file: PathBuf::from("tasks.toml"),
+            project: false,
+        };
+        let mut tasks = Tasks::new(&location);
+        for (number, title) in titles.iter().enumerate() {
+            let mut task = Task::new(title.to_string(), None, None, None, None, None);
+            // Random uuids could happen to start like a word being tested
+            task.uuid = Uuid::from_u128(number as u128 + 1);
+            tasks.push(task);
+        }
+        tasks
+    }
+
+    fn titles(tasks: &Tasks, selected: &[Uuid]) -> Vec<String> {
+        selected
+            .iter()
+            .map(|uuid| tasks.tasks[tasks.index_of(*uuid).unwrap()].title.clone())
+            .collect()
+    }
+
+    fn parse(expression: &str) -> Result<Filter, InertiaError> {
+        Filter::parse(&[expression.to_string()])
+    }
+
+    #[test]
+    fn parses_terms() {
+        let filter = parse(r#"status:done -tag:Someday area:none "write report""#).unwrap();
+        assert_eq!(
+            filter.terms,
+            [
+                (false, Term::Status(Status::Complete)),
+                (true, Term::Tag(Some(String::from("someday")))),
+                (false, Term::Area(None)),
+                (false, Term::Title(String::from("write report"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_terms() {
+        for term in [
+            "status:sleeping",
+            "colour:red",
+            "tag:",
+            "area~home",
+            "due.during:friday",
+        ] {
+            assert!(parse(term).is_err(), "{} should be rejected", term);
+        }
+        assert!(parse("tag:work --yes").is_err());
+    }
+
+    #[test]
+    fn matches_every_term() {
+        let mut tasks = tasks(&["email bob", "email alice", "call bob"]);
+        tasks.tasks[0].tags = Some(vec![String::from("work")]);
+        tasks.tasks[1].tags = Some(vec![String::from("work/admin")]);
+
+        let selected = tasks.select("email tag:work").unwrap();
+        assert_eq!(titles(&tasks, &selected), ["email bob", "email alice"]);
+        let selected = tasks.select("-tag:work").unwrap();
+        assert_eq!(titles(&tasks, &selected), ["call bob"]);
+        assert!(tasks.select("tag:home").is_err());
+    }
+
+    #[test]
+    fn keys_are_ids_ranges_and_uuids() {
+        let mut tasks = tasks(&["cafe menu", "two"]);
+        tasks.tasks[0].uuid = Uuid::parse_str("0bad0000-0000-4000-8000-000000000000").unwrap();
+
+        for key in ["1", "12", "3-9", "12345678", "0bad", "0BAD0000-0000"] {
+            assert!(tasks.is_key(key), "{} should be a key", key);
+        }
+        for word in ["", "-1", "cafe", "bad", "tag:work", "two"] {
+            assert!(!tasks.is_key(word), "{} shouldn't be a key", word);
+        }
+    }
+
+    #[test]
+    fn short_hex_words_search_titles() {
+        let tasks = tasks(&["cafe menu", "two"]);
+        let selected = tasks.select("cafe").unwrap();
+        assert_eq!(titles(&tasks, &selected), ["cafe menu"]);
+    }
+
+    #[test]
+    fn selects_lists_and_ranges() {
+        let tasks = tasks(&["one", "two", "three", "four"]);
+        let selected = tasks.select("1,3-4,3").unwrap();
+        assert_eq!(titles(&tasks, &selected), ["one", "three", "four"]);
+    }
+
+    #[test]
+    fn reversed_ranges_are_an_error() {
+        let tasks = tasks(&["one", "two"]);
+        assert!(matches!(
+            tasks.select("2-1"),
+            Err(InertiaError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn ranges_need_both_ends_to_be_tasks() {
+        let tasks = tasks(&["one", "two"]);
+        assert_eq!(tasks.range("1-5").unwrap(), None);
+        assert!(tasks.select("1-5").is_err());
+    }
+}
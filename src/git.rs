@@ -0,0 +1,558 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use git2::build::CheckoutBuilder;
+use git2::{
+    BranchType, Commit, Cred, CredentialType, ErrorCode, FetchOptions, IndexAddOption, IndexEntry,
+    IndexTime, Oid, PushOptions, RemoteCallbacks, ResetType, Signature, Sort, StatusOptions,
+};
+use std::cell::RefCell;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::error::InertiaError;
+
+fn error(err: git2::Error) -> InertiaError {
+    InertiaError::git(err.message())
+}
+
+/// Git paths always use forward slashes
+fn git_path(file: &Path) -> String {
+    file.to_string_lossy().replace('\\', "/")
+}
+
+/// A commit in the history of the repository
+pub struct CommitInfo {
+    pub id: String,             // The full id of the commit
+    pub date: NaiveDateTime,    // When it was committed, in local time
+    pub subject: String,        // The first line of its message, describing the change
+    pub message: String,        // Its whole message, including any trailers
+    pub parent: Option<String>, // Its first parent, or None for the first commit
+    pub merge: bool,            // Whether it merges other commits
+}
+
+/// A git repository, worked with in-process rather than by running git
+pub struct Repository {
+    repo: git2::Repository,
+}
+
+impl Repository {
+    /// Opens the repository at a directory, without looking in its parents
+    pub fn open(path: &Path) -> Result<Self, InertiaError> {
+        let repo = git2::Repository::open(path).map_err(error)?;
+        Ok(Self { repo })
+    }
+
+    pub fn init(path: &Path) -> Result<Self, InertiaError> {
+        let repo = git2::Repository::init(path).map_err(error)?;
+        Ok(Self { repo })
+    }
+
+    /// Finds the repository a directory is in, if it's in one with a work tree
+    pub fn discover(dir: &Path) -> Option<Self> {
+        git2::Repository::discover(dir)
+            .ok()
+            .filter(|repo| !repo.is_bare())
+            .map(|repo| Self { repo })
+    }
+
+    /// The directory the repository's files are checked out in
+    pub fn workdir(&self) -> Option<&Path> {
+        self.repo.workdir()
+    }
+
+    /// The directory git keeps its own files in, such as .git, which worktrees and submodules keep elsewhere
+    pub fn git_dir(&self) -> &Path {
+        self.repo.path()
+    }
+
+    /// The file of patterns git ignores in this repository only
+    pub fn exclude_file(&self) -> PathBuf {
+        self.repo.path().join("info").join("exclude")
+    }
+
+    pub fn config(&self, key: &str) -> Option<String> {
+        self.repo.config().ok()?.get_string(key).ok()
+    }
+
+    pub fn set_config(&self, key: &str, value: &str) -> Result<(), InertiaError> {
+        self.repo
+            .config()
+            .and_then(|mut config| config.set_str(key, value))
+            .map_err(error)
+    }
+
+    /// Uses the same identity git would, falling back to a default so commits work on fresh machines
+    fn signature(&self) -> Result<Signature<'static>, InertiaError> {
+        if let (Ok(name), Ok(email)) = (env::var("GIT_AUTHOR_NAME"), env::var("GIT_AUTHOR_EMAIL")) {
+            return Signature::now(&name, &email).map_err(error);
+        }
+        match self.repo.signature() {
+            Ok(signature) => Ok(signature),
+            Err(_) => Signature::now("inertia", "inertia@localhost").map_err(error),
+        }
+    }
+
+    fn find_commit(&self, commit: &str) -> Result<Commit<'_>, InertiaError> {
+        self.repo
+            .revparse_single(commit)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(error)
+    }
+
+    /// Finds the full id of a commit, branch or other ref, or None if it doesn't exist
+    pub fn resolve(&self, commit: &str) -> Result<Option<String>, InertiaError> {
+        match self.find_commit(commit) {
+            Ok(commit) => Ok(Some(commit.id().to_string())),
+            Err(_) if self.repo.revparse_single(commit).is_err() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the commit HEAD points to, or None before the first commit
+    pub fn head(&self) -> Result<Option<String>, InertiaError> {
+        match self.repo.head() {
+            Ok(head) => Ok(head.target().map(|id| id.to_string())),
+            Err(err) if err.code() == ErrorCode::UnbornBranch => Ok(None),
+            Err(err) => Err(error(err)),
+        }
+    }
+
+    /// Returns the branch that is checked out
+    pub fn branch(&self) -> Result<String, InertiaError> {
+        let head = self.repo.find_reference("HEAD").map_err(error)?;
+        let target = head.symbolic_target().unwrap_or("HEAD");
+        Ok(target.trim_start_matches("refs/heads/").to_string())
+    }
+
+    /// Returns the remote branch HEAD is tracking, if it has one
+    pub fn upstream(&self) -> Result<Option<String>, InertiaError> {
+        let branch = match self.repo.find_branch(&self.branch()?, BranchType::Local) {
+            Ok(branch) => branch,
+            Err(_) => return Ok(None),
+        };
+        Ok(branch
+            .upstream()
+            .ok()
+            .and_then(|upstream| upstream.name().ok().flatten().map(str::to_string)))
+    }
+
+    /// Returns the contents of a file at a commit, or None if it didn't exist then
+    pub fn file_at(&self, commit: &str, file: &Path) -> Result<Option<String>, InertiaError> {
+        let tree = self.find_commit(commit)?.tree().map_err(error)?;
+        let entry = match tree.get_path(Path::new(&git_path(file))) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+        let blob = self.repo.find_blob(entry.id()).map_err(error)?;
+        Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+    }
+
+    /// Stages every change, including deleted files
+    pub fn add_all(&self) -> Result<(), InertiaError> {
+        let mut index = self.repo.index().map_err(error)?;
+        index
+            .add_all(["*"], IndexAddOption::DEFAULT, None)
+            .and_then(|_| index.update_all(["*"], None))
+            .and_then(|_| index.write())
+            .map_err(error)
+    }
+
+    /// Stages a file, given relative to the work tree
+    pub fn add(&self, file: &Path) -> Result<(), InertiaError> {
+        let mut index = self.repo.index().map_err(error)?;
+        index
+            .add_path(Path::new(&git_path(file)))
+            .and_then(|_| index.write())
+            .map_err(error)
+    }
+
+    /// Whether there are no changes, staged or not, including new files
+    pub fn is_clean(&self) -> Result<bool, InertiaError> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).exclude_submodules(true);
+        let statuses = self.repo.statuses(Some(&mut options)).map_err(error)?;
+        Ok(statuses.is_empty())
+    }
+
+    /// Whether anything has been staged since the last commit
+    pub fn has_staged(&self) -> Result<bool, InertiaError> {
+        let staged = self
+            .repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .map_err(error)?;
+        match self.head()? {
+            Some(head) => Ok(self.find_commit(&head)?.tree_id() != staged),
+            None => Ok(true),
+        }
+    }
+
+    fn commit_tree(
+        &self,
+        tree: Oid,
+        message: &str,
+        parents: &[&Commit],
+    ) -> Result<Oid, InertiaError> {
+        let signature = self.signature()?;
+        let tree = self.repo.find_tree(tree).map_err(error)?;
+        self.repo
+            .commit(None, &signature, &signature, message, &tree, parents)
+            .map_err(error)
+    }
+
+    /// Moves the checked out branch to a commit, leaving the index and files alone
+    fn set_head(&self, commit: Oid, message: &str) -> Result<(), InertiaError> {
+        let branch = format!("refs/heads/{}", self.branch()?);
+        self.repo
+            .reference(&branch, commit, true, message)
+            .map(|_| ())
+            .map_err(error)
+    }
+
+    /// Commits everything that has been staged
+    pub fn commit(&self, message: &str) -> Result<(), InertiaError> {
+        let tree = self
+            .repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .map_err(error)?;
+        let parent = match self.head()? {
+            Some(head) => Some(self.find_commit(&head)?),
+            None => None,
+        };
+        let commit = self.commit_tree(tree, message, &parent.iter().collect::<Vec<_>>())?;
+        self.set_head(commit, message)
+    }
+
+    /// Commits a file in the top of the work tree on its own, leaving anything else that's staged
+    pub fn commit_file(&self, file: &str, message: &str) -> Result<(), InertiaError> {
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| InertiaError::git("the repository has no work tree"))?;
+        let blob = self.repo.blob_path(&workdir.join(file)).map_err(error)?;
+        self.add(Path::new(file))?;
+
+        let head = self
+            .head()?
+            .ok_or_else(|| InertiaError::git("nothing has been committed yet"))?;
+        let parent = self.find_commit(&head)?;
+        let mut tree = self
+            .repo
+            .treebuilder(Some(&parent.tree().map_err(error)?))
+            .map_err(error)?;
+        tree.insert(file, blob, 0o100644).map_err(error)?;
+        let tree = tree.write().map_err(error)?;
+
+        let commit = self.commit_tree(tree, message, &[&parent])?;
+        self.set_head(commit, message)
+    }
+
+    /// Commits the files as they were at an older commit, undoing everything since without losing history
+    pub fn revert_to(&self, commit: &str, message: &str) -> Result<(), InertiaError> {
+        let tree = self.find_commit(commit)?.tree_id();
+        let head = self
+            .head()?
+            .ok_or_else(|| InertiaError::git("nothing has been committed yet"))?;
+        let commit = self.commit_tree(tree, message, &[&self.find_commit(&head)?])?;
+        self.set_head(commit, message)?;
+        self.reset_hard(&commit.to_string())
+    }
+
+    /// Moves HEAD to a commit, throwing away any changes
+    pub fn reset_hard(&self, commit: &str) -> Result<(), InertiaError> {
+        let commit = self.find_commit(commit)?;
+        self.repo
+            .reset(commit.as_object(), ResetType::Hard, None)
+            .map_err(error)
+    }
+
+    /// Returns the subjects of the commits after one commit up to another, newest first
+    pub fn subjects(&self, from: &str, to: &str) -> Result<Vec<String>, InertiaError> {
+        self.commits(from, to)?
+            .iter()
+            .map(|commit| {
+                let commit = self.find_commit(commit)?;
+                Ok(commit.summary().unwrap_or_default().to_string())
+            })
+            .collect()
+    }
+
+    /// Returns the commits after one commit up to another, newest first
+    pub fn commits(&self, from: &str, to: &str) -> Result<Vec<String>, InertiaError> {
+        let mut walk = self.repo.revwalk().map_err(error)?;
+        walk.set_sorting(Sort::TOPOLOGICAL).map_err(error)?;
+        walk.push(self.find_commit(to)?.id()).map_err(error)?;
+        walk.hide(self.find_commit(from)?.id()).map_err(error)?;
+        walk.map(|id| id.map(|id| id.to_string()).map_err(error))
+            .collect()
+    }
+
+    /// Returns the commits up to and including a commit, oldest first, optionally only those made since a date
+    pub fn log(
+        &self,
+        tip: &str,
+        since: Option<NaiveDateTime>,
+    ) -> Result<Vec<CommitInfo>, InertiaError> {
+        let Some(tip) = self.resolve(tip)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut walk = self.repo.revwalk().map_err(error)?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)
+            .map_err(error)?;
+        walk.push(self.find_commit(&tip)?.id()).map_err(error)?;
+
+        let mut commits = Vec::new();
+        for id in walk {
+            let commit = self.repo.find_commit(id.map_err(error)?).map_err(error)?;
+            let date = match Local.timestamp_opt(commit.time().seconds(), 0).single() {
+                Some(date) => date.naive_local(),
+                None => continue,
+            };
+            if since.is_some_and(|since| date < since) {
+                continue;
+            }
+
+            commits.push(CommitInfo {
+                id: commit.id().to_string(),
+                date,
+                subject: commit.summary().unwrap_or_default().to_string(),
+                message: commit.message().unwrap_or_default().to_string(),
+                parent: commit.parent_id(0).ok().map(|id| id.to_string()),
+                merge: commit.parent_count() > 1,
+            });
+        }
+        Ok(commits)
+    }
+
+    /// Counts the commits up to and including a commit
+    pub fn count(&self, commit: &str) -> Result<usize, InertiaError> {
+        let mut walk = self.repo.revwalk().map_err(error)?;
+        walk.push(self.find_commit(commit)?.id()).map_err(error)?;
+        Ok(walk.count())
+    }
+
+    /// Whether one commit comes before another in its history, or is the same commit
+    pub fn is_ancestor(&self, ancestor: &str, commit: &str) -> Result<bool, InertiaError> {
+        let ancestor = self.find_commit(ancestor)?.id();
+        let commit = self.find_commit(commit)?.id();
+        Ok(ancestor == commit
+            || self
+                .repo
+                .graph_descendant_of(commit, ancestor)
+                .map_err(error)?)
+    }
+
+    /// Finds the commit HEAD last had in common with another, if they share any history
+    pub fn merge_base(&self, other: &str) -> Result<Option<String>, InertiaError> {
+        let head = self.find_commit("HEAD")?.id();
+        let other = self.find_commit(other)?.id();
+        match self.repo.merge_base(head, other) {
+            Ok(base) => Ok(Some(base.to_string())),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(error(err)),
+        }
+    }
+
+    /// Counts the commits HEAD has that another doesn't, and the other way round
+    pub fn ahead_behind(&self, other: &str) -> Result<(usize, usize), InertiaError> {
+        let head = self.find_commit("HEAD")?.id();
+        let other = self.find_commit(other)?.id();
+        self.repo.graph_ahead_behind(head, other).map_err(error)
+    }
+
+    /// Whether any remote branch has a commit, meaning it has been synced
+    pub fn is_pushed(&self, commit: &str) -> Result<bool, InertiaError> {
+        let commit = self.find_commit(commit)?.id();
+        for branch in self
+            .repo
+            .branches(Some(BranchType::Remote))
+            .map_err(error)?
+        {
+            let (branch, _) = branch.map_err(error)?;
+            if let Some(target) = branch.get().target() {
+                if target == commit
+                    || self
+                        .repo
+                        .graph_descendant_of(target, commit)
+                        .map_err(error)?
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the commit a ref points to, if it exists
+    pub fn reference(&self, name: &str) -> Result<Option<String>, InertiaError> {
+        match self.repo.find_reference(name) {
+            Ok(reference) => Ok(reference.target().map(|id| id.to_string())),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(error(err)),
+        }
+    }
+
+    pub fn set_reference(&self, name: &str, commit: &str) -> Result<(), InertiaError> {
+        let commit = self.find_commit(commit)?.id();
+        self.repo
+            .reference(name, commit, true, "inertia")
+            .map(|_| ())
+            .map_err(error)
+    }
+
+    pub fn delete_reference(&self, name: &str) -> Result<(), InertiaError> {
+        match self.repo.find_reference(name) {
+            Ok(mut reference) => reference.delete().map_err(error),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(()),
+            Err(err) => Err(error(err)),
+        }
+    }
+
+    /// Updates the remote branches from a remote
+    pub fn fetch(&self, remote: &str) -> Result<(), InertiaError> {
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(self.callbacks());
+
+        let mut remote = self.repo.find_remote(remote).map_err(error)?;
+        remote
+            .fetch(&[] as &[&str], Some(&mut options), None)
+            .map_err(error)
+    }
+
+    /// Logs in to remotes the way git would, with the ssh agent or git's credential helpers
+    fn callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut attempts = 0;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed| {
+            // Git keeps asking while logging in fails, so give up rather than asking forever
+            attempts += 1;
+            if attempts > 3 {
+                return Err(git2::Error::from_str("couldn't log in to the remote"));
+            }
+
+            if allowed.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                let config = self.repo.config()?;
+                Cred::credential_helper(&config, url, username)
+            } else {
+                Cred::default()
+            }
+        });
+        callbacks
+    }
+
+    /// Pushes a branch to a remote, tracking it from then on
+    pub fn push(&self, remote: &str, branch: &str) -> Result<(), InertiaError> {
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        let rejected = RefCell::new(None);
+        {
+            let mut callbacks = self.callbacks();
+            callbacks.push_update_reference(|_, status| {
+                *rejected.borrow_mut() = status.map(str::to_string);
+                Ok(())
+            });
+            let mut options = PushOptions::new();
+            options.remote_callbacks(callbacks);
+
+            let mut remote = self.repo.find_remote(remote).map_err(error)?;
+            remote
+                .push(&[&refspec], Some(&mut options))
+                .map_err(error)?;
+        }
+        if let Some(reason) = rejected.into_inner() {
+            return Err(InertiaError::git(&format!(
+                "{} rejected {}: {}",
+                remote, branch, reason
+            )));
+        }
+
+        let mut local = self
+            .repo
+            .find_branch(branch, BranchType::Local)
+            .map_err(error)?;
+        local
+            .set_upstream(Some(&format!("{}/{}", remote, branch)))
+            .map_err(error)
+    }
+
+    /// Merges another commit into HEAD, merging a file changed on both sides with `resolve`
+    ///
+    /// Nothing is changed unless everything can be merged.
+    pub fn merge<F>(&self, other: &str, file: &Path, resolve: F) -> Result<(), InertiaError>
+    where
+        F: Fn(&str, &str, &str) -> Result<String, InertiaError>,
+    {
+        let ours = self.find_commit("HEAD")?;
+        let theirs = self.find_commit(other)?;
+        if self.is_ancestor(&theirs.id().to_string(), "HEAD")? {
+            return Ok(());
+        }
+
+        // Fast forward when there's nothing of ours to merge
+        let message = format!("merge {}", other);
+        if self.is_ancestor("HEAD", &theirs.id().to_string())? {
+            self.checkout(theirs.tree_id())?;
+            return self.set_head(theirs.id(), &message);
+        }
+
+        let mut index = self
+            .repo
+            .merge_commits(&ours, &theirs, None)
+            .map_err(error)?;
+
+        // Git can only merge the file line by line, so it's merged as a whole instead
+        let base = match self.merge_base(other)? {
+            Some(base) => self.file_at(&base, file)?,
+            None => None,
+        };
+        let versions = (self.file_at("HEAD", file)?, self.file_at(other, file)?);
+        if let (Some(ours), Some(theirs)) = versions {
+            if ours != theirs && Some(&ours) != base.as_ref() && Some(&theirs) != base.as_ref() {
+                let merged = resolve(base.as_deref().unwrap_or_default(), &ours, &theirs)?;
+                let blob = self.repo.blob(merged.as_bytes()).map_err(error)?;
+                let path = git_path(file);
+                let entry = IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: 0o100644,
+                    uid: 0,
+                    gid: 0,
+                    file_size: merged.len() as u32,
+                    id: blob,
+                    flags: path.len().min(0xfff) as u16,
+                    flags_extended: 0,
+                    path: path.clone().into_bytes(),
+                };
+                index.remove_path(Path::new(&path)).map_err(error)?;
+                index.add(&entry).map_err(error)?;
+            }
+        }
+
+        if index.has_conflicts() {
+            let files = index
+                .conflicts()
+                .map_err(error)?
+                .filter_map(|conflict| conflict.ok())
+                .filter_map(|conflict| conflict.our.or(conflict.their))
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                .collect::<Vec<_>>();
+            return Err(InertiaError::merge_failed(other, &files));
+        }
+
+        let tree = index.write_tree_to(&self.repo).map_err(error)?;
+        let commit = self.commit_tree(tree, &message, &[&ours, &theirs])?;
+        self.checkout(tree)?;
+        self.set_head(commit, &message)
+    }
+
+    /// Checks out a tree over the current one, keeping any files with changes of their own
+    fn checkout(&self, tree: Oid) -> Result<(), InertiaError> {
+        let tree = self.repo.find_tree(tree).map_err(error)?;
+        self.repo
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(error)
+    }
+}
@@ -0,0 +1,480 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::git::Repository;
+use crate::merge::CONFLICT_TAG;
+use crate::repo;
+use crate::tasks::{Task, Tasks};
+
+/// Trailer added to the commits moving tasks from another profile, so their history can be followed there
+const MOVED_FROM: &str = "Moved-from: ";
+
+/// Tasks can't have been moved more times than this, which stops a broken trailer sending `log` round in circles
+const MAX_MOVES: usize = 100;
+
+/// Describes where moved tasks came from, to be added to the end of the commit that moves them
+pub fn moved_trailer(commit: &str, path: &Path) -> String {
+    format!("{}{} {}", MOVED_FROM, commit, path.display())
+}
+
+/// Finds where the tasks in a commit were moved from, as the commit and repository they came from
+fn moved_from(message: &str) -> Option<(String, PathBuf)> {
+    let trailer = message
+        .lines()
+        .find_map(|line| line.strip_prefix(MOVED_FROM))?;
+    let (commit, path) = trailer.split_once(' ')?;
+    Some((commit.to_string(), PathBuf::from(path)))
+}
+
+/// How the tasks changed between two versions, counted in tasks rather than commits
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Changes {
+    pub added: usize,     // Tasks that are new
+    pub deleted: usize,   // Tasks that were deleted without being completed
+    pub completed: usize, // Tasks that were completed
+    pub reopened: usize,  // Completed tasks that are no longer complete
+    pub modified: usize,  // Tasks that changed in any other way
+    pub conflicts: usize, // Conflict tasks from merging clashing changes
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = [
+            (self.added, "added"),
+            (self.deleted, "deleted"),
+            (self.completed, "completed"),
+            (self.reopened, "reopened"),
+            (self.modified, "modified"),
+            (self.conflicts, "conflicts"),
+        ];
+        let parts: Vec<String> = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, what)| format!("{} {}", count, what))
+            .collect();
+
+        if parts.is_empty() {
+            write!(f, "no task changes")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Tasks are compared as TOML, the same way they're saved
+fn same(before: &Task, after: &Task) -> bool {
+    toml::Value::try_from(before).ok() == toml::Value::try_from(after).ok()
+}
+
+/// Counts how the tasks changed from one version to another, where None means the file didn't exist
+pub fn changes(before: Option<&Tasks>, after: Option<&Tasks>) -> Changes {
+    let before: HashMap<Uuid, &Task> = before
+        .map(|tasks| tasks.tasks.iter().map(|task| (task.uuid, task)).collect())
+        .unwrap_or_default();
+    let after_tasks: &[Task] = after
+        .map(|tasks| tasks.tasks.as_slice())
+        .unwrap_or_default();
+
+    let mut changes = Changes::default();
+    for task in after_tasks {
+        match before.get(&task.uuid) {
+            None if task.tags.iter().flatten().any(|tag| tag == CONFLICT_TAG) => {
+                changes.conflicts += 1
+            }
+            None => changes.added += 1,
+            Some(old) if old.is_complete() != task.is_complete() => {
+                if task.is_complete() {
+                    changes.completed += 1;
+                } else {
+                    changes.reopened += 1;
+                }
+            }
+            Some(old) if !same(old, task) => changes.modified += 1,
+            Some(_) => {}
+        }
+    }
+
+    // Completed tasks that are deleted have already been counted when they were completed
+    changes.deleted = before
+        .values()
+        .filter(|task| !task.is_complete())
+        .filter(|task| !after_tasks.iter().any(|after| after.uuid == task.uuid))
+        .count();
+
+    changes
+}
+
+/// Loads the tasks as they were at a commit, or None if the tasks file didn't exist yet
+pub fn tasks_at(
+    repo: &Repository,
+    file: &Path,
+    commit: &str,
+) -> Result<Option<Tasks>, InertiaError> {
+    match repo.file_at(commit, file)? {
+        Some(data) => repo::parse_tasks(&Path::new(commit).join(file), &data).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Counts how the tasks changed between two commits
+pub fn changes_between(
+    repo: &Repository,
+    file: &Path,
+    from: Option<&str>,
+    to: &str,
+) -> Result<Changes, InertiaError> {
+    let before = match from {
+        Some(from) => tasks_at(repo, file, from)?,
+        None => None,
+    };
+    let after = tasks_at(repo, file, to)?;
+    Ok(changes(before.as_ref(), after.as_ref()))
+}
+
+/// The value of a field of a task, kept as a date where it is one so it can be shown in any format
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    Date(NaiveDateTime),
+}
+
+/// One field of a task that changed in a commit
+pub struct FieldChange {
+    pub field: &'static str,        // The name of the field, such as "deadline"
+    pub before: Option<FieldValue>, // What it was, or None if it wasn't set
+    pub after: Option<FieldValue>,  // What it became, or None if it was cleared
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Created, // The task was added
+    Deleted, // The task was removed
+    Changed, // Some of the task's fields changed
+    Moved,   // The task was moved here from another profile
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Created => "created",
+            EventKind::Deleted => "deleted",
+            EventKind::Changed => "changed",
+            EventKind::Moved => "moved",
+        }
+    }
+}
+
+/// How one task changed in one commit
+pub struct Event {
+    pub commit: String,            // The id of the commit that made the change
+    pub date: NaiveDateTime,       // When it was committed
+    pub subject: String,           // The commit message describing the change
+    pub uuid: Uuid,                // The uuid of the task that changed
+    pub id: usize,                 // Its short id at the time
+    pub title: String,             // Its title at the time, or before it was deleted
+    pub kind: EventKind,           // Whether it was created, deleted or changed
+    pub changes: Vec<FieldChange>, // The fields that changed, or were set when it was created
+    pub moved_from: Option<(String, PathBuf)>, // The commit and repository it was moved from
+}
+
+/// The fields of a task worth following, leaving out the short id which is given out again when tasks are deleted
+fn fields(task: Option<&Task>) -> [(&'static str, Option<FieldValue>); 9] {
+    let text = |value: fn(&Task) -> Option<String>| task.and_then(value).map(FieldValue::Text);
+    let date =
+        |value: fn(&Task) -> Option<NaiveDateTime>| task.and_then(value).map(FieldValue::Date);
+    [
+        ("title", text(|task| Some(task.title.clone()))),
+        (
+            "status",
+            text(|task| Some(task.status.as_str().to_string())),
+        ),
+        ("area", text(|task| task.area.clone())),
+        (
+            "tags",
+            text(|task| task.tags.as_ref().map(|tags| tags.join(", "))),
+        ),
+        ("when", date(|task| task.when)),
+        ("deadline", date(|task| task.deadline)),
+        ("reminder", date(|task| task.reminder)),
+        (
+            "repeats",
+            text(|task| task.recurrence.as_ref().map(|rule| rule.to_string())),
+        ),
+        ("notes", text(|task| task.notes.clone())),
+    ]
+}
+
+/// Lists the fields that differ between two versions of a task, where None means it didn't exist
+fn field_changes(before: Option<&Task>, after: Option<&Task>) -> Vec<FieldChange> {
+    fields(before)
+        .into_iter()
+        .zip(fields(after))
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((field, before), (_, after))| FieldChange {
+            field,
+            before,
+            after,
+        })
+        .collect()
+}
+
+/// Lists how tasks changed in each commit up to a commit since a date, oldest first, optionally for only one task
+pub fn events(
+    repo: &Repository,
+    file: &Path,
+    tip: &str,
+    since: Option<NaiveDateTime>,
+    uuid: Option<Uuid>,
+) -> Result<Vec<Event>, InertiaError> {
+    let mut events = Vec::new();
+    let mut previous: Option<(String, Option<Tasks>)> = None;
+
+    for commit in repo.log(tip, since)? {
+        let moved_from = moved_from(&commit.message);
+
+        // Merges only bring in changes that were made, and are listed, in the commits they merge
+        if commit.merge {
+            previous = None;
+            continue;
+        }
+
+        // Commits usually follow on from the one before, so its tasks don't need loading again
+        let before = match (previous.take(), &commit.parent) {
+            (Some((id, tasks)), Some(parent)) if id == *parent => tasks,
+            (_, Some(parent)) => tasks_at(repo, file, parent)?,
+            (_, None) => None,
+        };
+        let after = tasks_at(repo, file, &commit.id)?;
+
+        let before_tasks: HashMap<Uuid, &Task> = before
+            .iter()
+            .flat_map(|tasks| tasks.tasks.iter())
+            .map(|task| (task.uuid, task))
+            .collect();
+        let after_tasks: HashMap<Uuid, &Task> = after
+            .iter()
+            .flat_map(|tasks| tasks.tasks.iter())
+            .map(|task| (task.uuid, task))
+            .collect();
+
+        // Tasks are listed in the order they are in the file, followed by any that were deleted
+        let uuids = after
+            .iter()
+            .flat_map(|tasks| tasks.tasks.iter())
+            .chain(before.iter().flat_map(|tasks| tasks.tasks.iter()))
+            .map(|task| task.uuid)
+            .filter(|task| uuid.is_none() || uuid == Some(*task));
+
+        let mut seen = HashSet::new();
+        for task in uuids {
+            if !seen.insert(task) {
+                continue;
+            }
+
+            let old = before_tasks.get(&task).copied();
+            let new = after_tasks.get(&task).copied();
+            let (kind, current) = match (old, new) {
+                (None, Some(new)) if moved_from.is_some() => (EventKind::Moved, new),
+                (None, Some(new)) => (EventKind::Created, new),
+                (Some(old), None) => (EventKind::Deleted, old),
+                (Some(_), Some(new)) => (EventKind::Changed, new),
+                (None, None) => continue,
+            };
+
+            let changes = match kind {
+                EventKind::Deleted | EventKind::Moved => Vec::new(),
+                _ => field_changes(old, new),
+            };
+            if kind == EventKind::Changed && changes.is_empty() {
+                continue;
+            }
+
+            events.push(Event {
+                commit: commit.id.clone(),
+                date: commit.date,
+                subject: commit.subject.clone(),
+                uuid: task,
+                id: current.id,
+                title: current.title.clone(),
+                kind,
+                changes,
+                moved_from: moved_from.clone().filter(|_| kind == EventKind::Moved),
+            });
+        }
+
+        previous = Some((commit.id, after));
+    }
+
+    Ok(events)
+}
+
+/// Lists how a task changed since a date, following it back through any profiles it was moved from
+pub fn task_events(
+    repo: &Repository,
+    file: &Path,
+    since: Option<NaiveDateTime>,
+    uuid: Uuid,
+) -> Result<Vec<Event>, InertiaError> {
+    let mut found = events(repo, file, "HEAD", since, Some(uuid))?;
+
+    for _ in 0..MAX_MOVES {
+        let Some((commit, path)) = found.first().and_then(|event| event.moved_from.clone()) else {
+            break;
+        };
+
+        // The other profile may have been removed since, leaving only the history from here
+        let Ok(source) = Repository::open(&path) else {
+            break;
+        };
+        let mut earlier = events(&source, file, &commit, since, Some(uuid))?;
+        if earlier.is_empty() {
+            break;
+        }
+        earlier.append(&mut found);
+        found = earlier;
+    }
+
+    Ok(found)
+}
+
+/// How a task differs between two versions of the tasks, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiffKind {
+    Added,       // The task is new
+    Removed,     // The task was deleted, or cleared once complete
+    Completed,   // The task was completed
+    Reopened,    // The task is no longer complete
+    Rescheduled, // The task's when, deadline or reminder changed
+}
+
+impl DiffKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiffKind::Added => "added",
+            DiffKind::Removed => "removed",
+            DiffKind::Completed => "completed",
+            DiffKind::Reopened => "reopened",
+            DiffKind::Rescheduled => "rescheduled",
+        }
+    }
+}
+
+/// A task that differs between two versions of the tasks
+pub struct TaskDiff<'a> {
+    pub kind: DiffKind,            // How it differs
+    pub tasks: &'a Tasks, // The version of the tasks it's from, the older one if it was removed
+    pub task: &'a Task,   // The task itself
+    pub changes: Vec<FieldChange>, // The dates that changed if it was rescheduled
+}
+
+/// Lists the tasks that were added, removed, completed or rescheduled from one version to another
+pub fn diff<'a>(before: Option<&'a Tasks>, after: Option<&'a Tasks>) -> Vec<TaskDiff<'a>> {
+    let before_tasks: HashMap<Uuid, &Task> = before
+        .iter()
+        .flat_map(|tasks| tasks.tasks.iter())
+        .map(|task| (task.uuid, task))
+        .collect();
+    let after_uuids: HashSet<Uuid> = after
+        .iter()
+        .flat_map(|tasks| tasks.tasks.iter())
+        .map(|task| task.uuid)
+        .collect();
+
+    let mut diffs = Vec::new();
+    if let Some(tasks) = after {
+        for task in &tasks.tasks {
+            let old = before_tasks.get(&task.uuid).copied();
+            let changes: Vec<FieldChange> = field_changes(old, Some(task))
+                .into_iter()
+                .filter(|change| matches!(change.field, "when" | "deadline" | "reminder"))
+                .collect();
+
+            let kind = match old {
+                None => DiffKind::Added,
+                Some(old) if old.is_complete() != task.is_complete() => {
+                    if task.is_complete() {
+                        DiffKind::Completed
+                    } else {
+                        DiffKind::Reopened
+                    }
+                }
+                Some(_) if !changes.is_empty() => DiffKind::Rescheduled,
+                Some(_) => continue,
+            };
+
+            diffs.push(TaskDiff {
+                kind,
+                tasks,
+                task,
+                changes: match kind {
+                    DiffKind::Rescheduled => changes,
+                    _ => Vec::new(),
+                },
+            });
+        }
+    }
+
+    if let Some(tasks) = before {
+        for task in &tasks.tasks {
+            if !after_uuids.contains(&task.uuid) {
+                diffs.push(TaskDiff {
+                    kind: DiffKind::Removed,
+                    tasks,
+                    task,
+                    changes: Vec::new(),
+                });
+            }
+        }
+    }
+
+    // Group them by how they differ, keeping the order of the tasks within each group
+    diffs.sort_by_key(|diff| diff.kind);
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A tasks file from before tasks had uuids or ids, as found in old commits
+    const V0: &str = r#"
+[[tasks]]
+title = "first"
+status = "Inbox"
+
+[[tasks]]
+title = "second"
+status = "Inbox"
+"#;
+
+    fn parse(data: &str) -> Tasks {
+        repo::parse_tasks(Path::new("tasks.toml"), data).unwrap()
+    }
+
+    #[test]
+    fn identical_old_commits_have_no_differences() {
+        let (before, after) = (parse(V0), parse(V0));
+        assert!(diff(Some(&before), Some(&after)).is_empty());
+    }
+
+    #[test]
+    fn old_commits_match_tasks_up() {
+        let before = parse(V0);
+        let after = parse(&V0.replacen("Inbox", "Complete", 1));
+        let diffs = diff(Some(&before), Some(&after));
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].kind, DiffKind::Completed);
+        assert_eq!(diffs[0].task.title, "first");
+    }
+}
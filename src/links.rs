@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::tasks::{Task, Tasks};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Blocks,      // The linked task can't be done until this one is complete
+    Related,     // The tasks are related to each other
+    DuplicateOf, // This task is a duplicate of the linked task
+}
+
+impl LinkKind {
+    /// Describes the link from the task it's stored on
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkKind::Blocks => "blocks",
+            LinkKind::Related => "related to",
+            LinkKind::DuplicateOf => "duplicate of",
+        }
+    }
+
+    /// Describes the link from the task it points to
+    pub fn as_inverse_str(&self) -> &'static str {
+        match self {
+            LinkKind::Blocks => "blocked by",
+            LinkKind::Related => "related to",
+            LinkKind::DuplicateOf => "duplicated by",
+        }
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub kind: LinkKind, // How the tasks are linked
+    pub task: Uuid,     // The uuid of the linked task
+}
+
+impl Tasks {
+    /// Checks if `to` can be reached from `from` by following links of one kind
+    fn reaches(&self, from: Uuid, to: Uuid, kind: LinkKind) -> bool {
+        let mut stack = vec![from];
+        let mut seen = vec![];
+
+        while let Some(uuid) = stack.pop() {
+            if uuid == to {
+                return true;
+            }
+            if seen.contains(&uuid) {
+                continue;
+            }
+            seen.push(uuid);
+
+            if let Some(task) = self.tasks.iter().find(|task| task.uuid == uuid) {
+                let next = task.links.iter().filter(|link| link.kind == kind);
+                stack.extend(next.map(|link| link.task));
+            }
+        }
+
+        false
+    }
+
+    /// Checks a task can link to another, which has to exist and not already lead back to it
+    fn check_link(&self, from: &Task, kind: LinkKind, to: Uuid) -> Result<(), InertiaError> {
+        let Some(to) = self.tasks.iter().find(|task| task.uuid == to) else {
+            return Err(InertiaError::invalid_link(&format!(
+                "there's no task with uuid {}",
+                to
+            )));
+        };
+
+        if from.uuid == to.uuid {
+            return Err(InertiaError::invalid_link(
+                "a task can't be linked to itself",
+            ));
+        }
+
+        // Blocking and duplicate links can't go round in a circle
+        if kind != LinkKind::Related && self.reaches(to.uuid, from.uuid, kind) {
+            return Err(InertiaError::invalid_link(&format!(
+                "linking would create a cycle, as {} is already {} {}",
+                from.id,
+                kind.as_inverse_str(),
+                to.id
+            )));
+        }
+
+        // Related links go both ways, so one from the other task already links them
+        let back = Link {
+            kind,
+            task: from.uuid,
+        };
+        if kind == LinkKind::Related && to.links.contains(&back) {
+            return Err(InertiaError::invalid_link("the tasks are already linked"));
+        }
+
+        Ok(())
+    }
+
+    /// Links the task at index `from` to the task at index `to`
+    pub fn link(&mut self, from: usize, kind: LinkKind, to: usize) -> Result<(), InertiaError> {
+        let to_uuid = self.tasks[to].uuid;
+        self.check_link(&self.tasks[from], kind, to_uuid)?;
+
+        let link = Link {
+            kind,
+            task: to_uuid,
+        };
+        if self.tasks[from].links.contains(&link) {
+            return Err(InertiaError::invalid_link("the tasks are already linked"));
+        }
+        self.tasks[from].links.push(link);
+
+        Ok(())
+    }
+
+    /// Checks the links of a changed version of a task, such as one edited by hand, like `link` would
+    pub fn check_links(&self, task: &Task) -> Result<(), InertiaError> {
+        for (index, link) in task.links.iter().enumerate() {
+            self.check_link(task, link.kind, link.task)?;
+            if task.links[..index].contains(link) {
+                return Err(InertiaError::invalid_link("the tasks are already linked"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes every link between two tasks, returning how many were removed
+    pub fn unlink(&mut self, a: usize, b: usize) -> usize {
+        let a_uuid = self.tasks[a].uuid;
+        let b_uuid = self.tasks[b].uuid;
+
+        let before = self.tasks[a].links.len() + self.tasks[b].links.len();
+        self.tasks[a].links.retain(|link| link.task != b_uuid);
+        self.tasks[b].links.retain(|link| link.task != a_uuid);
+        before - self.tasks[a].links.len() - self.tasks[b].links.len()
+    }
+
+    /// Removes links pointing to a task that no longer exists
+    pub fn forget_links(&mut self, uuid: Uuid) {
+        for task in self.tasks.iter_mut() {
+            task.links.retain(|link| link.task != uuid);
+        }
+    }
+
+    /// Returns every link to or from a task, described from the task's side
+    pub fn links(&self, task: &Task) -> Vec<(&'static str, &Task)> {
+        let mut links = vec![];
+
+        for link in &task.links {
+            if let Some(other) = self.tasks.iter().find(|other| other.uuid == link.task) {
+                links.push((link.kind.as_str(), other));
+            }
+        }
+        for other in &self.tasks {
+            for link in other.links.iter().filter(|link| link.task == task.uuid) {
+                links.push((link.kind.as_inverse_str(), other));
+            }
+        }
+
+        links
+    }
+
+    /// Returns the uncompleted tasks that block a task
+    pub fn blockers(&self, task: &Task) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|other| !other.is_complete())
+            .filter(|other| {
+                other
+                    .links
+                    .iter()
+                    .any(|link| link.kind == LinkKind::Blocks && link.task == task.uuid)
+            })
+            .collect()
+    }
+
+    /// Checks if a task can't be done until other tasks are complete
+    pub fn is_blocked(&self, task: &Task) -> bool {
+        !self.blockers(task).is_empty()
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::output;
+use crate::error::InertiaError;
+
+/// How often to check if a lock has been released
+const RETRY_EVERY: Duration = Duration::from_millis(100);
+
+/// A lock on a tasks file, so only one inertia can change it at a time, released when dropped
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf, // The lock file, which holds the pid of the process holding the lock
+}
+
+impl Lock {
+    /// Waits for a lock, taking over locks left behind by processes that have crashed
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self, InertiaError> {
+        let started = Instant::now();
+        loop {
+            // Creating the file fails if it already exists, so only one process can hold the lock
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    let lock = Self {
+                        path: path.to_path_buf(),
+                    };
+                    writeln!(file, "{}", process::id())
+                        .map_err(|err| InertiaError::io(path, &err.to_string()))?;
+                    return Ok(lock);
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(InertiaError::io(path, &err.to_string())),
+            }
+
+            // The lock may have been released in the meantime, or not have its pid written yet
+            let owner = read_pid(path);
+            match owner {
+                Some(pid) if !is_running(pid) => remove_stale(path, pid)?,
+                _ if started.elapsed() >= timeout => {
+                    return Err(InertiaError::locked(path, owner));
+                }
+                _ => thread::sleep(RETRY_EVERY),
+            }
+        }
+    }
+}
+
+/// Reads the pid of the process holding a lock
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+}
+
+/// Removes a lock left by a process that isn't running, unless another waiter has already replaced it
+fn remove_stale(path: &Path, pid: u32) -> Result<(), InertiaError> {
+    // Moving the lock aside is atomic, so each lock file can only be taken by one waiter
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".{}.stale", process::id()));
+    let aside = PathBuf::from(aside);
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(InertiaError::io(path, &err.to_string())),
+    }
+
+    // Another waiter may have removed the stale lock and taken its own since we read it, so put that back
+    if read_pid(&aside) == Some(pid) {
+        output::warning(format!("removed a stale lock left by pid {}", pid));
+    } else {
+        // Linking fails if the lock exists, so a newer lock is never overwritten
+        let _ = fs::hard_link(&aside, path);
+    }
+    fs::remove_file(&aside).map_err(|err| InertiaError::io(&aside, &err.to_string()))
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // A lock taken over while we were stuck belongs to someone else now, so it's left alone
+        if read_pid(&self.path) == Some(process::id()) {
+            // Nothing can be done if the lock file has already gone
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // Signal 0 checks the process exists without sending it anything
+    // SAFETY: kill has no memory safety requirements
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    // Without a way to check, locks are only given up on after the timeout
+    true
+}
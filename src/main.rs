@@ -1,40 +1,109 @@
+mod areas;
 mod args;
 mod cli;
+mod config;
+mod error;
+mod filter;
+mod git;
+mod history;
+mod links;
+mod lock;
+mod merge;
+mod next;
+mod recurrence;
 mod repo;
+mod tags;
 mod tasks;
 
 use clap::Parser;
-use colored::*;
+use std::process;
 
-use crate::args::TasksArgs;
+use crate::args::{
+    Commands, ConfigCommand, InitProject, MergeFiles, MoveTask, ProfileCommand, TasksArgs,
+};
+use crate::cli::output;
+use crate::error::InertiaError;
 
-fn main() {
-    // Generate the file paths for tasks
-    let repo_path = repo::tasks_repo_string();
-    let tasks_file_path = repo::tasks_file_path();
-
-    // If the tasks file doesn't exist, create it first
-    match repo::ensure_repo(&repo_path) {
-        Ok(..) => (),
-        Err(error) => panic!("{} {:?}", "error:".red().bold(), error),
+fn run(arguments: TasksArgs) -> Result<(), InertiaError> {
+    // Settings and profiles can be changed without a tasks repository, even if they are broken
+    let arguments = match arguments.command {
+        Commands::Config(ConfigCommand { command }) => return cli::config::execute(command),
+        Commands::Profile(ProfileCommand { command }) => return cli::profiles::execute(command),
+        // Git runs the merge driver on temporary copies of the tasks file, not the repository
+        Commands::MergeDriver(MergeFiles { base, ours, theirs }) => {
+            return cli::git::merge_driver(&base, &ours, &theirs)
+        }
+        _ => arguments,
     };
 
-    // Load tasks and check for any errors when loading the tasks
-    let mut tasks = match repo::load_tasks(&tasks_file_path) {
-        Ok(tasks) => tasks,
-        Err(error) => panic!("{} {:?}", "error:".red().bold(), error),
+    // Load the config file, turning colours off if they aren't wanted
+    config::load()?;
+    if !config::get().display.colors {
+        colored::control::set_override(false);
+    }
+
+    // New projects don't have any tasks to load yet
+    if let Commands::Init(InitProject { file }) = arguments.command {
+        return cli::projects::init(file);
+    }
+
+    // Find the tasks, in the project we're in or the tasks repository
+    let location = repo::locate(
+        arguments.repo.clone(),
+        arguments.profile.as_deref(),
+        arguments.global,
+    )?;
+
+    // If the tasks repository doesn't exist, create it first
+    if !location.project {
+        repo::ensure_repo(&location.path)?;
+    }
+
+    // Moving tasks changes another profile's tasks too, so they need locking as well
+    let target = match &arguments.command {
+        Commands::Move(MoveTask { to, .. }) => Some(cli::profiles::move_target(&location, to)?),
+        _ => None,
     };
 
-    // Parse command line arguments
-    let arguments = TasksArgs::parse();
+    // Hold a lock until the changes are saved, so other inertias don't overwrite them
+    let locations: Vec<&repo::Location> = [Some(&location), target.as_ref()]
+        .into_iter()
+        .flatten()
+        .collect();
+    let _locks = repo::lock_all(&locations)?;
+
+    // Load tasks and check for any errors when loading the tasks
+    let mut tasks = repo::load_tasks(&location)?;
 
     // Execute the inputted command line arguments
-    match cli::execute(&mut tasks, arguments) {
-        Ok(..) => (),
-        Err(error) => panic!("{} {:?}", "error:".red().bold(), error),
-    };
+    let no_commit = arguments.no_commit;
+    let message = cli::execute(&mut tasks, arguments)?;
+
+    // Save any changes and commit them, so they can be undone
+    if let Some(message) = message {
+        repo::save_tasks(location.tasks_file(), &tasks)?;
+
+        if location.project {
+            // Project tasks are committed along with the project's code
+            repo::stage_project(&location)?;
+        } else if no_commit {
+            repo::stage(&location.path, &message)?;
+        } else {
+            repo::commit(&location.path, &message)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    // Parse command line arguments
+    let arguments = TasksArgs::parse();
+    output::set_format(arguments.output);
 
-    // Save any changes
-    repo::save_tasks(&tasks_file_path, &tasks).unwrap();
-    repo::execute(&repo_path, String::from("add --all")).unwrap();
+    // Report errors without a backtrace, exiting with a code scripts can check
+    if let Err(error) = run(arguments) {
+        output::error(error.to_string());
+        process::exit(error.exit_code());
+    }
 }
@@ -0,0 +1,355 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use toml::{Table, Value};
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::repo;
+
+/// Tag given to the tasks created for changes that couldn't be merged
+pub const CONFLICT_TAG: &str = "conflict";
+
+/// The tasks from merging two changed versions of the same tasks
+pub struct Merged {
+    pub table: Table,     // The merged tasks file
+    pub conflicts: usize, // How many tasks were changed differently on each side
+}
+
+enum Field<'a> {
+    Merged(Option<&'a Value>), // Both sides agree, or only one side changed it
+    Clash,                     // Both sides changed it to something different
+}
+
+/// Merges one value that may have been changed on either side
+fn merge_field<'a>(
+    base: Option<&'a Value>,
+    ours: Option<&'a Value>,
+    theirs: Option<&'a Value>,
+) -> Field<'a> {
+    if ours == theirs || theirs == base {
+        Field::Merged(ours)
+    } else if ours == base {
+        Field::Merged(theirs)
+    } else {
+        Field::Clash
+    }
+}
+
+/// Merges a task field by field, returning the fields that clashed, which keep our value
+fn merge_task(base: Option<&Table>, ours: &Table, theirs: &Table) -> (Table, Vec<String>) {
+    let keys: BTreeSet<&String> = ours
+        .keys()
+        .chain(theirs.keys())
+        .chain(base.into_iter().flat_map(|base| base.keys()))
+        .collect();
+
+    let mut merged = Table::new();
+    let mut clashes = Vec::new();
+    for key in keys {
+        let base_value = base.and_then(|base| base.get(key));
+        let field = merge_field(base_value, ours.get(key), theirs.get(key));
+        let value = match field {
+            Field::Merged(value) => value,
+            // Short ids are given out separately on each machine, so they aren't worth a conflict
+            Field::Clash if key == "id" => ours.get(key),
+            Field::Clash => {
+                clashes.push(key.clone());
+                ours.get(key)
+            }
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    (merged, clashes)
+}
+
+/// Makes a task holding their side of a clash, so nothing is lost and it's easy to find and resolve
+fn conflict_task(ours: &Table, theirs: &Table, clashes: &[String]) -> Table {
+    let mut task = theirs.clone();
+    let uuid = ours.get("uuid").and_then(Value::as_str).unwrap_or("?");
+    let title = theirs.get("title").and_then(Value::as_str).unwrap_or("");
+
+    task.insert(
+        String::from("uuid"),
+        Value::from(Uuid::new_v4().to_string()),
+    );
+    task.insert(String::from("id"), Value::from(0));
+    task.insert(
+        String::from("title"),
+        Value::from(format!("conflict: {}", title)),
+    );
+    task.remove("links");
+
+    // Tag it, so `inertia show tag:conflict` lists everything that needs resolving
+    let mut tags = match task.remove("tags") {
+        Some(Value::Array(tags)) => tags,
+        _ => Vec::new(),
+    };
+    tags.push(Value::from(CONFLICT_TAG));
+    task.insert(String::from("tags"), Value::Array(tags));
+
+    let note = format!(
+        "{} changed differently on another machine than in task {}, this is the other version",
+        clashes.join(", "),
+        uuid
+    );
+    let notes = match theirs.get("notes").and_then(Value::as_str) {
+        Some(notes) => format!("{}\n\n{}", note, notes),
+        None => note,
+    };
+    task.insert(String::from("notes"), Value::from(notes));
+
+    task
+}
+
+/// Tasks are matched up by uuid, which tasks from older files are given when they're upgraded
+fn task_key(task: &Table) -> String {
+    task.get("uuid")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Returns the tables in an array of tables, such as the tasks or areas
+fn tables<'a>(file: &'a Table, key: &str) -> Vec<&'a Table> {
+    match file.get(key) {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_table).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Merges lists of tables matched up by a key, keeping our order with their new items after
+fn merge_list(
+    base: &[&Table],
+    ours: &[&Table],
+    theirs: &[&Table],
+    key: fn(&Table) -> String,
+    conflicts: &mut Vec<Table>,
+) -> Vec<Table> {
+    let base: HashMap<String, &Table> = base.iter().map(|item| (key(item), *item)).collect();
+    let theirs_by_key: HashMap<String, &Table> =
+        theirs.iter().map(|item| (key(item), *item)).collect();
+    let ours_keys: HashSet<String> = ours.iter().map(|item| key(item)).collect();
+
+    let mut merged = Vec::new();
+    for item in ours {
+        let item_key = key(item);
+        match (base.get(&item_key), theirs_by_key.get(&item_key)) {
+            (base, Some(theirs)) => {
+                let (item, clashes) = merge_task(base.copied(), item, theirs);
+                if !clashes.is_empty() {
+                    conflicts.push(conflict_task(&item, theirs, &clashes));
+                }
+                merged.push(item);
+            }
+            // They deleted it, which only stands if we didn't change it
+            (Some(base), None) if *base == *item => {}
+            _ => merged.push((*item).clone()),
+        }
+    }
+    for item in theirs {
+        let item_key = key(item);
+        if ours_keys.contains(&item_key) {
+            continue;
+        }
+        // We deleted it, which only stands if they didn't change it
+        match base.get(&item_key) {
+            Some(base) if *base == *item => {}
+            _ => merged.push((*item).clone()),
+        }
+    }
+
+    merged
+}
+
+/// Makes sure every task has its own short id, clearing duplicates so they are given new ones
+fn dedupe_ids(tasks: &mut [Table]) {
+    let mut seen = HashSet::new();
+    for task in tasks {
+        let id = task.get("id").and_then(Value::as_integer).unwrap_or(0);
+        if id != 0 && !seen.insert(id) {
+            task.insert(String::from("id"), Value::from(0));
+        }
+    }
+}
+
+/// Merges the tasks files changed on each side since their common base, task by task and field by field
+pub fn merge(base: &Table, ours: &Table, theirs: &Table) -> Merged {
+    let mut conflicts = Vec::new();
+
+    // Tasks are matched by uuid, so independent changes to the same task are both kept
+    let mut tasks = merge_list(
+        &tables(base, "tasks"),
+        &tables(ours, "tasks"),
+        &tables(theirs, "tasks"),
+        task_key,
+        &mut conflicts,
+    );
+
+    // Areas are matched by name, with clashes keeping our version
+    let area_key = |area: &Table| {
+        area.get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    let areas = merge_list(
+        &tables(base, "areas"),
+        &tables(ours, "areas"),
+        &tables(theirs, "areas"),
+        area_key,
+        &mut Vec::new(),
+    );
+
+    let count = conflicts.len();
+    tasks.extend(conflicts);
+    dedupe_ids(&mut tasks);
+
+    // Everything else is merged as a whole, keeping ours if both changed
+    let (mut table, _) = merge_task(Some(base), ours, theirs);
+    let next_id = [ours, theirs]
+        .iter()
+        .filter_map(|file| file.get("next_id").and_then(Value::as_integer))
+        .max()
+        .unwrap_or(1);
+    table.insert(String::from("next_id"), Value::from(next_id));
+    table.insert(
+        String::from("areas"),
+        Value::Array(areas.into_iter().map(Value::Table).collect()),
+    );
+    table.insert(
+        String::from("tasks"),
+        Value::Array(tasks.into_iter().map(Value::Table).collect()),
+    );
+
+    Merged {
+        table,
+        conflicts: count,
+    }
+}
+
+/// Merges the contents of three tasks files, returning the merged file and how many tasks conflicted
+pub fn merge_data(
+    path: &Path,
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<(String, usize), InertiaError> {
+    let (base, _) = repo::parse_table(path, base)?;
+    let (ours, _) = repo::parse_table(path, ours)?;
+    let (theirs, _) = repo::parse_table(path, theirs)?;
+    let merged = merge(&base, &ours, &theirs);
+
+    // Loading the tasks gives new ids to tasks that ended up sharing one, and any conflict tasks
+    let tasks = repo::from_table(path, merged.table)?;
+
+    let data =
+        toml::to_string_pretty(&tasks).map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+    Ok((data, merged.conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::Tasks;
+
+    const BASE: &str = r#"
+version = 1
+next_id = 3
+
+[[tasks]]
+uuid = "00000000-0000-4000-8000-000000000001"
+id = 1
+title = "first"
+status = "Inbox"
+
+[[tasks]]
+uuid = "00000000-0000-4000-8000-000000000002"
+id = 2
+title = "second"
+status = "Inbox"
+"#;
+
+    fn merge_files(base: &str, ours: &str, theirs: &str) -> (Tasks, usize) {
+        let path = Path::new("tasks.toml");
+        let (data, conflicts) = merge_data(path, base, ours, theirs).unwrap();
+        (repo::parse_tasks(path, &data).unwrap(), conflicts)
+    }
+
+    fn titles(tasks: &Tasks) -> Vec<&str> {
+        tasks.tasks.iter().map(|task| task.title.as_str()).collect()
+    }
+
+    #[test]
+    fn changes_to_different_fields_are_both_kept() {
+        let ours = BASE.replacen("\"first\"", "\"first, renamed\"", 1);
+        let theirs = BASE.replacen("Inbox", "Complete", 1);
+        let (tasks, conflicts) = merge_files(BASE, &ours, &theirs);
+
+        assert_eq!(conflicts, 0);
+        assert_eq!(titles(&tasks), ["first, renamed", "second"]);
+        assert!(tasks.tasks[0].is_complete());
+    }
+
+    #[test]
+    fn clashing_changes_make_a_conflict_task() {
+        let ours = BASE.replacen("\"first\"", "\"ours\"", 1);
+        let theirs = BASE.replacen("\"first\"", "\"theirs\"", 1);
+        let (tasks, conflicts) = merge_files(BASE, &ours, &theirs);
+
+        assert_eq!(conflicts, 1);
+        assert_eq!(titles(&tasks), ["ours", "second", "conflict: theirs"]);
+        let conflict = &tasks.tasks[2];
+        assert!(conflict
+            .tags
+            .as_ref()
+            .unwrap()
+            .contains(&String::from(CONFLICT_TAG)));
+        assert_ne!(conflict.id, 0);
+    }
+
+    #[test]
+    fn deletions_only_stand_if_the_other_side_didnt_change_the_task() {
+        let start = BASE.find("[[tasks]]").unwrap();
+        let second = BASE.rfind("[[tasks]]").unwrap();
+        let without_first = format!("{}{}", &BASE[..start], &BASE[second..]);
+        let (tasks, _) = merge_files(BASE, &without_first, BASE);
+        assert_eq!(titles(&tasks), ["second"]);
+
+        let changed = BASE.replacen("Inbox", "Complete", 1);
+        let (tasks, _) = merge_files(BASE, &without_first, &changed);
+        assert_eq!(titles(&tasks), ["second", "first"]);
+    }
+
+    #[test]
+    fn tasks_added_on_both_sides_get_their_own_ids() {
+        let added = |uuid: &str, title: &str| {
+            format!(
+                "{}\n[[tasks]]\nuuid = \"{}\"\nid = 3\ntitle = \"{}\"\nstatus = \"Inbox\"\n",
+                BASE, uuid, title
+            )
+        };
+        let ours = added("00000000-0000-4000-8000-000000000003", "ours");
+        let theirs = added("00000000-0000-4000-8000-000000000004", "theirs");
+        let (tasks, _) = merge_files(BASE, &ours, &theirs);
+
+        assert_eq!(titles(&tasks), ["first", "second", "ours", "theirs"]);
+        let ids: HashSet<usize> = tasks.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn files_from_before_uuids_are_matched_up() {
+        let base = "[[tasks]]\ntitle = \"first\"\nstatus = \"Inbox\"\n\n[[tasks]]\ntitle = \"second\"\nstatus = \"Inbox\"\n";
+        let ours = base.replacen("Inbox", "Complete", 1);
+        let theirs = base.replace("Inbox", "Active");
+        let (tasks, conflicts) = merge_files(base, &ours, &theirs);
+
+        assert_eq!(conflicts, 1);
+        assert_eq!(titles(&tasks), ["first", "second", "conflict: first"]);
+        assert!(tasks.tasks[0].is_complete());
+        assert!(tasks.tasks[1].is_active());
+    }
+}
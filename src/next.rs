@@ -0,0 +1,148 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::config;
+use crate::filter::Filter;
+use crate::tasks::{Task, Tasks};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    pub active: f64,                // Tasks that have already been started
+    pub scheduled: f64,             // Tasks with a when date of today or earlier
+    pub deadline: f64,              // Tasks with a deadline coming up, scaled by how close it is
+    pub deadline_days: f64,         // How many days ahead a deadline starts to count
+    pub overdue: f64,               // Tasks past their deadline, on top of the deadline weight
+    pub age: f64,                   // Added for every day since a task was created
+    pub inbox: f64,                 // Tasks that haven't been given a when date yet
+    pub tags: HashMap<String, f64>, // Added for tasks with each tag
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            active: 10.0,
+            scheduled: 8.0,
+            deadline: 10.0,
+            deadline_days: 7.0,
+            overdue: 5.0,
+            age: 0.1,
+            inbox: -1.0,
+            tags: HashMap::new(),
+        }
+    }
+}
+
+pub struct Recommendation {
+    pub index: usize,         // Index of the task in the tasks
+    pub score: f64,           // Higher scores should be done first
+    pub reasons: Vec<String>, // Why the task scored highly, most important first
+}
+
+fn days_between(from: NaiveDateTime, to: NaiveDateTime) -> i64 {
+    (to.date() - from.date()).num_days()
+}
+
+fn plural(days: i64) -> String {
+    if days == 1 {
+        String::from("1 day")
+    } else {
+        format!("{} days", days)
+    }
+}
+
+/// Scores a task, returning each part of the score with the reason for it
+fn factors(task: &Task, weights: &Weights, now: NaiveDateTime) -> Vec<(f64, String)> {
+    let mut factors = Vec::new();
+
+    if task.is_active() {
+        factors.push((weights.active, String::from("already started")));
+    } else if task.is_inbox() {
+        factors.push((weights.inbox, String::from("in the inbox")));
+    }
+
+    if let Some(when) = task.when {
+        let days = days_between(now, when);
+        if days < 0 {
+            factors.push((
+                weights.scheduled,
+                format!("scheduled {} ago", plural(-days)),
+            ));
+        } else if days == 0 {
+            factors.push((weights.scheduled, String::from("scheduled today")));
+        } else if days == 1 {
+            factors.push((weights.scheduled / 2.0, String::from("scheduled tomorrow")));
+        }
+    }
+
+    if let Some(deadline) = task.deadline {
+        let days = days_between(now, deadline);
+        if days < 0 {
+            let weight = weights.deadline + weights.overdue;
+            factors.push((weight, format!("overdue by {}", plural(-days))));
+        } else if (days as f64) <= weights.deadline_days {
+            // The closer the deadline, the more of the weight it gets
+            let closeness = 1.0 - days as f64 / (weights.deadline_days + 1.0);
+            let reason = match days {
+                0 => String::from("deadline today"),
+                1 => String::from("deadline tomorrow"),
+                _ => format!("deadline in {}", plural(days)),
+            };
+            factors.push((weights.deadline * closeness, reason));
+        }
+    }
+
+    if let Some(created) = task.created {
+        let days = days_between(created, now);
+        if days > 0 {
+            factors.push((
+                weights.age * days as f64,
+                format!("waiting {}", plural(days)),
+            ));
+        }
+    }
+
+    for (tag, weight) in &weights.tags {
+        if task.has_tag(tag) {
+            factors.push((*weight, format!("tagged {}", tag)));
+        }
+    }
+
+    factors
+}
+
+/// Recommends up to `number` tasks matching a filter that should be done next, best first
+pub fn recommend(
+    tasks: &Tasks,
+    filter: &Filter,
+    number: usize,
+    now: NaiveDateTime,
+) -> Vec<Recommendation> {
+    let mut recommendations: Vec<Recommendation> = tasks
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| !task.is_complete() && !tasks.in_archived_area(task))
+        .filter(|(_, task)| !tasks.is_blocked(task) && filter.matches(task))
+        .map(|(index, task)| {
+            let mut factors = factors(task, &config::get().next, now);
+            let score = factors.iter().map(|(weight, _)| weight).sum();
+
+            // Only the factors that pushed the task up are worth explaining
+            factors.retain(|(weight, _)| *weight > 0.0);
+            factors.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+            Recommendation {
+                index,
+                score,
+                reasons: factors.into_iter().map(|(_, reason)| reason).collect(),
+            }
+        })
+        .collect();
+
+    // Highest scores first, keeping older tasks first when scores tie
+    recommendations.sort_by(|a, b| b.score.total_cmp(&a.score));
+    recommendations.truncate(number);
+    recommendations
+}
@@ -0,0 +1,489 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::error::InertiaError;
+use crate::tasks::{Status, Task};
+
+/// Tasks can't repeat further apart than this many units, which keeps every occurrence a valid date
+const MAX_EVERY: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Day => "day",
+            Unit::Week => "week",
+            Unit::Month => "month",
+            Unit::Year => "year",
+        }
+    }
+
+    fn parse(word: &str) -> Option<Self> {
+        match word.strip_suffix('s').unwrap_or(word) {
+            "day" => Some(Unit::Day),
+            "week" => Some(Unit::Week),
+            "month" => Some(Unit::Month),
+            "year" => Some(Unit::Year),
+            _ => None,
+        }
+    }
+}
+
+/// How often a task repeats, stored as a rule such as "every 2 weeks"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub every: u32,             // How many units apart each occurrence is
+    pub unit: Unit,             // The unit of time the task repeats in
+    pub weekdays: Vec<Weekday>, // Days of the week the task falls on, for weekly tasks
+    pub day: Option<u32>, // Day of the month monthly and yearly tasks go back to after a shorter month
+    pub after_completion: bool, // Count from when the task was completed instead of its dates
+}
+
+/// Parses a repeat rule from the command line, where "never" stops a task repeating
+pub fn parse(rule: &str) -> Result<Option<Recurrence>, InertiaError> {
+    match rule.trim().to_lowercase().as_str() {
+        "never" | "none" => Ok(None),
+        rule => rule.parse().map(Some),
+    }
+}
+
+impl Recurrence {
+    /// Returns the first occurrence after a date, or None if it's too far in the future for a date
+    pub fn next(&self, date: NaiveDateTime) -> Option<NaiveDateTime> {
+        let every = self.every as i64;
+        match self.unit {
+            Unit::Day => date.checked_add_signed(Duration::days(every)),
+            Unit::Week if self.weekdays.is_empty() => {
+                date.checked_add_signed(Duration::weeks(every))
+            }
+            Unit::Week => {
+                // Step through the days, skipping whole weeks when a new one starts
+                let mut next = date;
+                loop {
+                    next = next.checked_add_signed(Duration::days(1))?;
+                    if next.weekday() == Weekday::Mon {
+                        next = next.checked_add_signed(Duration::weeks(every - 1))?;
+                    }
+                    if self.weekdays.contains(&next.weekday()) {
+                        return Some(next);
+                    }
+                }
+            }
+            Unit::Month | Unit::Year => {
+                let months = match self.unit {
+                    Unit::Year => self.every.checked_mul(12)?,
+                    _ => self.every,
+                };
+
+                // Months that are too short end up on their last day instead, going back to the day after
+                let next = date.checked_add_months(Months::new(months))?;
+                match self.day {
+                    Some(day) if date.day() == last_day(date.date())? && day > next.day() => {
+                        next.with_day(day.min(last_day(next.date())?))
+                    }
+                    _ => Some(next),
+                }
+            }
+        }
+    }
+}
+
+/// Returns the last day of the month a date is in
+fn last_day(date: NaiveDate) -> Option<u32> {
+    let first = date.with_day(1)?;
+    Some(first.checked_add_months(Months::new(1))?.pred_opt()?.day())
+}
+
+/// Parses a list of days of the week, sorted from Monday
+fn parse_weekdays(days: &[&str]) -> Option<Vec<Weekday>> {
+    let mut weekdays = Vec::new();
+    for day in days {
+        let day = day.parse::<Weekday>().ok()?;
+        if !weekdays.contains(&day) {
+            weekdays.push(day);
+        }
+    }
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    (!weekdays.is_empty()).then_some(weekdays)
+}
+
+impl FromStr for Recurrence {
+    type Err = InertiaError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let lowercase = rule.trim().to_lowercase();
+        let invalid = || InertiaError::invalid_recurrence(rule);
+
+        // Any rule can count from when the task was completed
+        let (lowercase, after_completion) = match lowercase.strip_suffix("after completion") {
+            Some(rest) => (rest, true),
+            None => (lowercase.as_str(), false),
+        };
+
+        let words: Vec<&str> = lowercase
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty() && *word != "and")
+            .collect();
+        let words = match words.split_first() {
+            Some((&"every", rest)) => rest,
+            _ => &words[..],
+        };
+
+        // Monthly and yearly rules can keep to a day of the month, such as "monthly on day 31"
+        let (words, day) = match words {
+            [rest @ .., "on", "day", day] => (rest, Some(day.parse().map_err(|_| invalid())?)),
+            _ => (words, None),
+        };
+
+        let mut recurrence = Self {
+            every: 1,
+            unit: Unit::Day,
+            weekdays: Vec::new(),
+            day,
+            after_completion,
+        };
+        match words {
+            ["daily"] => recurrence.unit = Unit::Day,
+            ["weekly"] => recurrence.unit = Unit::Week,
+            ["monthly"] => recurrence.unit = Unit::Month,
+            ["yearly"] | ["annually"] => recurrence.unit = Unit::Year,
+            ["weekday"] | ["weekdays"] => {
+                recurrence.unit = Unit::Week;
+                recurrence.weekdays = vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ];
+            }
+            [unit] if Unit::parse(unit).is_some() => recurrence.unit = Unit::parse(unit).unwrap(),
+            [every, unit] if Unit::parse(unit).is_some() => {
+                recurrence.every = every.parse().map_err(|_| invalid())?;
+                recurrence.unit = Unit::parse(unit).unwrap();
+            }
+            [every, unit, "on", days @ ..] if Unit::parse(unit) == Some(Unit::Week) => {
+                recurrence.every = every.parse().map_err(|_| invalid())?;
+                recurrence.unit = Unit::Week;
+                recurrence.weekdays = parse_weekdays(days).ok_or_else(invalid)?;
+            }
+            days => {
+                // Otherwise the rule should be a list of days, such as "every mon, thu"
+                recurrence.unit = Unit::Week;
+                recurrence.weekdays = parse_weekdays(days).ok_or_else(invalid)?;
+            }
+        }
+
+        // Particular days don't make sense counted from completion
+        let monthly = matches!(recurrence.unit, Unit::Month | Unit::Year);
+        if recurrence.every == 0
+            || recurrence.every > MAX_EVERY
+            || (after_completion && !recurrence.weekdays.is_empty())
+            || (day.is_some() && (after_completion || !monthly))
+            || day.is_some_and(|day| !(1..=31).contains(&day))
+        {
+            return Err(invalid());
+        }
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rule = if !self.weekdays.is_empty() {
+            let days: Vec<String> = self
+                .weekdays
+                .iter()
+                .map(|day| day.to_string().to_lowercase())
+                .collect();
+            if self.every == 1 {
+                format!("every {}", days.join(","))
+            } else {
+                format!("every {} weeks on {}", self.every, days.join(","))
+            }
+        } else if self.every == 1 && !self.after_completion {
+            match self.unit {
+                Unit::Day => String::from("daily"),
+                Unit::Week => String::from("weekly"),
+                Unit::Month => String::from("monthly"),
+                Unit::Year => String::from("yearly"),
+            }
+        } else if self.every == 1 {
+            format!("every {}", self.unit.as_str())
+        } else {
+            format!("every {} {}s", self.every, self.unit.as_str())
+        };
+
+        if let Some(day) = self.day {
+            write!(f, "{} on day {}", rule, day)
+        } else if self.after_completion {
+            write!(f, "{} after completion", rule)
+        } else {
+            write!(f, "{}", rule)
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = InertiaError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+impl Task {
+    /// Creates the next occurrence of a repeating task that was completed at `now`,
+    /// or None if its dates can't be moved any further
+    pub fn next_occurrence(&self, recurrence: &Recurrence, now: NaiveDateTime) -> Option<Task> {
+        let mut task = self.clone();
+        task.uuid = Uuid::new_v4();
+        task.id = 0;
+        task.links = Vec::new();
+        task.created = Some(now);
+
+        // Every date moves by the same amount, so they stay the same distance apart
+        match self.when.or(self.deadline).or(self.reminder) {
+            Some(anchor) => {
+                // Remember the day of tasks late in the month, before a short month moves them earlier
+                let mut recurrence = recurrence.clone();
+                let monthly = matches!(recurrence.unit, Unit::Month | Unit::Year);
+                if monthly
+                    && recurrence.day.is_none()
+                    && !recurrence.after_completion
+                    && anchor.day() > 28
+                {
+                    recurrence.day = Some(anchor.day());
+                    task.recurrence = Some(recurrence.clone());
+                }
+
+                let next = if recurrence.after_completion {
+                    recurrence.next(now.date().and_time(anchor.time()))?
+                } else {
+                    // Skip any occurrences that were missed while the task was overdue
+                    let mut next = recurrence.next(anchor)?;
+                    while next.date() <= now.date() {
+                        let following = recurrence.next(next)?;
+                        if following <= next {
+                            return None;
+                        }
+                        next = following;
+                    }
+                    next
+                };
+                let shift = next - anchor;
+                task.when = shift_date(self.when, shift)?;
+                task.deadline = shift_date(self.deadline, shift)?;
+                task.reminder = shift_date(self.reminder, shift)?;
+            }
+            // Tasks without any dates are scheduled for their next occurrence
+            None => task.when = Some(recurrence.next(now)?),
+        }
+
+        task.status = if task.when.is_some() {
+            Status::Pending
+        } else {
+            Status::Inbox
+        };
+        Some(task)
+    }
+}
+
+/// Moves a date that may not be set, returning None if it can't be moved that far
+fn shift_date(date: Option<NaiveDateTime>, shift: Duration) -> Option<Option<NaiveDateTime>> {
+    match date {
+        Some(date) => date.checked_add_signed(shift).map(Some),
+        None => Some(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    fn rule(rule: &str) -> Recurrence {
+        rule.parse().unwrap()
+    }
+
+    /// Completes a task due on a date, as if on that date, following its occurrences `count` times
+    fn occurrences(rule: &str, start: NaiveDateTime, count: usize) -> Vec<NaiveDateTime> {
+        let mut task = Task::new(
+            String::from("pay rent"),
+            None,
+            None,
+            Some(start),
+            None,
+            None,
+        );
+        task.recurrence = Some(self::rule(rule));
+
+        let mut dates = Vec::new();
+        for _ in 0..count {
+            let recurrence = task.recurrence.clone().unwrap();
+            task = task
+                .next_occurrence(&recurrence, task.when.unwrap())
+                .unwrap();
+            dates.push(task.when.unwrap());
+        }
+        dates
+    }
+
+    #[test]
+    fn rules_read_back_the_same() {
+        for text in [
+            "daily",
+            "weekly",
+            "monthly",
+            "yearly",
+            "every 3 days",
+            "every 2 weeks on mon,fri",
+            "every tue,thu",
+            "every week after completion",
+            "monthly on day 31",
+            "every 2 months on day 30",
+        ] {
+            assert_eq!(rule(text).to_string(), text);
+        }
+        assert_eq!(rule("Every Mon and Thu").to_string(), "every mon,thu");
+        assert_eq!(rule("weekdays").to_string(), "every mon,tue,wed,thu,fri");
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        for text in [
+            "",
+            "fortnightly",
+            "every 0 days",
+            "every 1001 days",
+            "every mon after completion",
+            "weekly on day 3",
+            "monthly on day 32",
+            "monthly on day 31 after completion",
+        ] {
+            assert!(
+                text.parse::<Recurrence>().is_err(),
+                "{} should be rejected",
+                text
+            );
+        }
+        assert_eq!(parse("never").unwrap(), None);
+    }
+
+    #[test]
+    fn steps_by_days_and_weeks() {
+        let monday = date(2026, 10, 5);
+        assert_eq!(rule("every 3 days").next(monday), Some(date(2026, 10, 8)));
+        assert_eq!(rule("every 2 weeks").next(monday), Some(date(2026, 10, 19)));
+        assert_eq!(rule("every mon,thu").next(monday), Some(date(2026, 10, 8)));
+        assert_eq!(
+            rule("every 2 weeks on mon").next(monday),
+            Some(date(2026, 10, 19))
+        );
+    }
+
+    #[test]
+    fn monthly_tasks_keep_their_day() {
+        assert_eq!(
+            occurrences("monthly", date(2027, 1, 31), 4),
+            [
+                date(2027, 2, 28),
+                date(2027, 3, 31),
+                date(2027, 4, 30),
+                date(2027, 5, 31)
+            ]
+        );
+        assert_eq!(
+            occurrences("monthly", date(2027, 1, 15), 2),
+            [date(2027, 2, 15), date(2027, 3, 15)]
+        );
+    }
+
+    #[test]
+    fn yearly_tasks_keep_leap_days() {
+        assert_eq!(
+            occurrences("yearly", date(2028, 2, 29), 4),
+            [
+                date(2029, 2, 28),
+                date(2030, 2, 28),
+                date(2031, 2, 28),
+                date(2032, 2, 29)
+            ]
+        );
+    }
+
+    #[test]
+    fn missed_occurrences_are_skipped() {
+        let mut task = Task::new(
+            String::from("water plants"),
+            None,
+            None,
+            Some(date(2026, 10, 1)),
+            None,
+            None,
+        );
+        task.deadline = Some(date(2026, 10, 2));
+        let next = task
+            .next_occurrence(&rule("weekly"), date(2026, 10, 20))
+            .unwrap();
+
+        assert_eq!(next.when, Some(date(2026, 10, 22)));
+        assert_eq!(next.deadline, Some(date(2026, 10, 23)));
+        assert_ne!(next.uuid, task.uuid);
+    }
+
+    #[test]
+    fn after_completion_counts_from_the_completion() {
+        let task = Task::new(
+            String::from("haircut"),
+            None,
+            None,
+            Some(date(2026, 9, 1)),
+            None,
+            None,
+        );
+        let completed = date(2026, 10, 20);
+        let next = task
+            .next_occurrence(&rule("every 4 weeks after completion"), completed)
+            .unwrap();
+        assert_eq!(next.when, Some(date(2026, 11, 17)));
+    }
+
+    #[test]
+    fn dates_too_far_away_stop_the_task_repeating() {
+        let task = Task::new(
+            String::from("far"),
+            None,
+            None,
+            Some(NaiveDateTime::MAX),
+            None,
+            None,
+        );
+        assert!(task
+            .next_occurrence(&rule("yearly"), date(2026, 10, 20))
+            .is_none());
+    }
+}
@@ -1,97 +1,832 @@
 use dirs::home_dir;
-use std::error::Error;
-use std::fs;
-use std::path::Path;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::string::ToString;
+use std::time::Duration;
+use uuid::{Builder, Uuid};
 
 use crate::cli::output;
+use crate::config;
+use crate::error::InertiaError;
+use crate::git::Repository;
+use crate::lock::Lock;
+use crate::merge;
+use crate::next::Weights;
 use crate::tasks::Tasks;
 
-const TASKS_FILE: &str = "tasks.toml";
+const REDO_REF: &str = "refs/inertia/redo";
+const PROJECT_DIR: &str = ".inertia";
+const PROJECT_FILE: &str = "inertia.toml";
+const BATCH_FILE: &str = "inertia-batch";
+const BACKUP_SUFFIX: &str = ".bak";
+const TEMP_SUFFIX: &str = ".tmp";
+const LOCK_SUFFIX: &str = ".lock";
+const ATTRIBUTES_FILE: &str = ".gitattributes";
+const MERGE_DRIVER: &str = "inertia";
 
-pub fn execute(path: &str, command: String) -> Result<(), Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["-C", path])
-        .args(command.split(' '))
-        .output()?;
+/// The version of the tasks file written by this inertia
+pub const VERSION: u32 = 2;
 
-    if !output.stdout.is_empty() {
-        output::git(String::from_utf8(output.stdout).unwrap());
+/// Steps that upgrade the tasks file, each from the version it's at in the list to the next
+const MIGRATIONS: [fn(&mut toml::Table); VERSION as usize] = [
+    // 0 to 1: the repository path and file name are found when loading, instead of being kept in the file,
+    // and tasks from before uuids and ids are given them
+    |table| {
+        table.remove("path");
+        table.remove("file");
+        identify_tasks(table);
+    },
+    // 1 to 2: the weights `next` scores tasks with are settings, so they're moved to the config file when loading
+    |table| {
+        table.remove("weights");
+    },
+];
+
+/// Gives tasks from before uuids and ids their own, the same ones every time the file or an old commit of it is read
+fn identify_tasks(table: &mut toml::Table) {
+    let Some(toml::Value::Array(tasks)) = table.get_mut("tasks") else {
+        return;
+    };
+
+    let id = |task: &toml::Value| task.get("id").and_then(toml::Value::as_integer);
+    let mut next_id = tasks.iter().filter_map(id).max().unwrap_or(0) + 1;
+    for (position, task) in tasks.iter_mut().enumerate() {
+        let unnumbered = id(task).unwrap_or(0) == 0;
+        let Some(task) = task.as_table_mut() else {
+            continue;
+        };
+        if !task.contains_key("uuid") {
+            let title = task
+                .get("title")
+                .and_then(toml::Value::as_str)
+                .unwrap_or("");
+            let uuid = legacy_uuid(position, title).to_string();
+            task.insert(String::from("uuid"), toml::Value::from(uuid));
+        }
+        if unnumbered {
+            task.insert(String::from("id"), toml::Value::from(next_id));
+            next_id += 1;
+        }
+    }
+
+    if !table.contains_key("next_id") {
+        table.insert(String::from("next_id"), toml::Value::from(next_id));
+    }
+}
+
+/// Makes a uuid for a task from before uuids out of where it is in the file and its title
+fn legacy_uuid(position: usize, title: &str) -> Uuid {
+    // FNV-1a, from two different starting points to fill all 16 bytes
+    let data = format!("{}\n{}", position, title);
+    let hash = |seed: u64| {
+        data.bytes().fold(seed, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
     };
-    if !output.stderr.is_empty() {
-        output::error(String::from_utf8(output.stderr).unwrap());
+
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&hash(0xcbf29ce484222325).to_be_bytes());
+    bytes[8..].copy_from_slice(&hash(0x84222325cbf29ce4).to_be_bytes());
+    Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// Where a list of tasks is kept
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf, // Directory git commands are run in
+    pub file: PathBuf, // The tasks file, relative to the directory
+    pub project: bool, // Whether the tasks belong to a project, and are committed along with its code
+}
+
+impl Location {
+    /// A tasks repository of its own, such as a profile's
+    pub fn repo(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: PathBuf::from(&config::get().repo.file),
+            project: false,
+        }
+    }
+
+    pub fn tasks_file(&self) -> PathBuf {
+        self.path.join(&self.file)
+    }
+
+    /// Locks the tasks, waiting for any other inertia changing them to finish
+    pub fn lock(&self) -> Result<Lock, InertiaError> {
+        let timeout = Duration::from_secs(config::get().repo.lock_timeout);
+        Lock::acquire(&with_suffix(&self.tasks_file(), LOCK_SUFFIX), timeout)
+    }
+
+    /// Checks if two locations are the same tasks, even when reached through different paths such as symlinks
+    pub fn is_same(&self, other: &Location) -> bool {
+        canonical(&self.tasks_file()) == canonical(&other.tasks_file())
+    }
+}
+
+/// Resolves a path to its canonical form where it exists, so different paths to the same place compare equal
+pub fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Locks several lists of tasks, always in the same order so two inertias can't each wait for the other
+pub fn lock_all(locations: &[&Location]) -> Result<Vec<Lock>, InertiaError> {
+    let mut locations = locations.to_vec();
+    locations.sort_by_key(|location| canonical(&location.tasks_file()));
+    locations.iter().map(|location| location.lock()).collect()
+}
+
+/// Runs a git command in the repository as it was typed, for anything inertia doesn't do itself
+pub fn execute(path: &Path, args: &[String]) -> Result<(), InertiaError> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .status()
+        .map_err(|err| InertiaError::git(&format!("couldn't run git: {}", err)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(InertiaError::git(&format!("git {} failed", args.join(" "))))
+    }
+}
+
+/// The file changes are listed in until they're committed, kept with git's own files
+fn batch_file(repo: &Repository) -> PathBuf {
+    repo.git_dir().join(BATCH_FILE)
+}
+
+/// Stages everything except inertia's own files, which clones don't know to ignore until now
+fn add_all(repo: &Repository) -> Result<(), InertiaError> {
+    ignore_backups(&repo.exclude_file(), Path::new(&config::get().repo.file))?;
+    repo.add_all()
+}
+
+/// Stages everything without committing, remembering the change for the next commit
+pub fn stage(path: &Path, message: &str) -> Result<(), InertiaError> {
+    let repo = Repository::open(path)?;
+    add_all(&repo)?;
+
+    let file = batch_file(&repo);
+    let mut batch = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .map_err(|err| InertiaError::io(&file, &err.to_string()))?;
+    writeln!(batch, "{}", message).map_err(|err| InertiaError::io(&file, &err.to_string()))?;
+
+    Ok(())
+}
+
+/// Returns the changes that have been staged but not committed yet
+pub fn batch(path: &Path) -> Result<Vec<String>, InertiaError> {
+    let file = batch_file(&Repository::open(path)?);
+    if file.exists() {
+        Ok(fs::read_to_string(&file)
+            .map_err(|err| InertiaError::io(&file, &err.to_string()))?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn clear_batch(repo: &Repository) -> Result<(), InertiaError> {
+    let file = batch_file(repo);
+    if file.exists() {
+        fs::remove_file(&file).map_err(|err| InertiaError::io(&file, &err.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Stages everything and commits it, returning whether anything changed
+pub fn commit(path: &Path, message: &str) -> Result<bool, InertiaError> {
+    let repo = Repository::open(path)?;
+    add_all(&repo)?;
+
+    // Nothing to commit if the staged tree matches HEAD, so any batch cancelled itself out
+    if !repo.has_staged()? {
+        clear_batch(&repo)?;
+        return Ok(false);
+    }
+
+    // List any batched changes that are going into this commit too
+    let batch = batch(path)?;
+    let message = if batch.is_empty() || batch == [message] {
+        message.to_string()
+    } else {
+        let body: Vec<String> = batch.iter().map(|change| format!("- {}", change)).collect();
+        format!("{}\n\n{}", message, body.join("\n"))
     };
+    repo.commit(&message)?;
+
+    // The batch has been committed
+    clear_batch(&repo)?;
+
+    // A new change means anything that was undone can no longer be redone
+    clear_redo(&repo)?;
+
+    Ok(true)
+}
+
+/// Returns the commit undone changes can be redone up to, if any
+pub fn redo_tip(repo: &Repository) -> Result<Option<String>, InertiaError> {
+    repo.reference(REDO_REF)
+}
+
+pub fn set_redo(repo: &Repository, commit: &str) -> Result<(), InertiaError> {
+    repo.set_reference(REDO_REF, commit)
+}
+
+pub fn clear_redo(repo: &Repository) -> Result<(), InertiaError> {
+    repo.delete_reference(REDO_REF)
+}
+
+/// Adds a suffix to a file name, such as tasks.toml.bak
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Writes a file without ever leaving it half written, by writing a temporary file and renaming it into place
+fn write_atomic(path: &Path, data: &str) -> Result<(), InertiaError> {
+    let temp = with_suffix(path, TEMP_SUFFIX);
+    let io_error = |err: std::io::Error| InertiaError::io(path, &err.to_string());
+
+    // Make sure the data is on disk before it replaces the old file
+    let mut file = File::create(&temp).map_err(io_error)?;
+    file.write_all(data.as_bytes()).map_err(io_error)?;
+    file.sync_all().map_err(io_error)?;
+    fs::rename(&temp, path).map_err(io_error)?;
+
+    // Then make sure the rename is on disk too
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(io_error)?;
+    }
+
+    Ok(())
+}
+
+/// Keeps the current version of a file as a backup, linking to it rather than copying where possible
+fn backup(path: &Path) -> Result<(), InertiaError> {
+    let backup = with_suffix(path, BACKUP_SUFFIX);
+    let io_error = |err: std::io::Error| InertiaError::io(&backup, &err.to_string());
 
+    if backup.exists() {
+        fs::remove_file(&backup).map_err(io_error)?;
+    }
+    if fs::hard_link(path, &backup).is_err() {
+        fs::copy(path, &backup).map_err(io_error)?;
+    }
     Ok(())
 }
 
-pub fn save_tasks<P: AsRef<Path>>(path: P, tasks: &Tasks) -> Result<(), Box<dyn Error>> {
+pub fn save_tasks<P: AsRef<Path>>(path: P, tasks: &Tasks) -> Result<(), InertiaError> {
+    let path = path.as_ref();
+
     // Convert the tasks to TOML format
-    let data = toml::to_string_pretty(&tasks)?;
+    let data =
+        toml::to_string_pretty(&tasks).map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+
+    // Keep the previous version, to recover from if this one can't be read
+    if path.exists() {
+        backup(path)?;
+    }
 
     // Write the TOML to the file
-    fs::write(path, data)?;
+    write_atomic(path, &data)
+}
 
-    Ok(())
+/// Finds which version of inertia a tasks file was written for, where files from before versions are 0
+fn file_version(path: &Path, table: &toml::Table) -> Result<u32, InertiaError> {
+    match table.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| InertiaError::toml(path, "version isn't a whole number")),
+    }
+}
+
+/// Reads a tasks file as TOML, upgraded to the current version, returning the version it was
+pub fn read_table(path: &Path) -> Result<(toml::Table, u32), InertiaError> {
+    let data = fs::read_to_string(path).map_err(|err| InertiaError::io(path, &err.to_string()))?;
+    parse_table(path, &data)
+}
+
+pub fn parse_table(path: &Path, data: &str) -> Result<(toml::Table, u32), InertiaError> {
+    let mut table: toml::Table =
+        toml::from_str(data).map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+
+    // Files from newer versions may have changes this inertia doesn't know how to read
+    let version = file_version(path, &table)?;
+    if version > VERSION {
+        return Err(InertiaError::newer_file(path, version, VERSION));
+    }
+
+    // Upgrade the file one version at a time
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut table);
+    }
+    table.insert(String::from("version"), toml::Value::from(VERSION));
+    Ok((table, version))
 }
 
-pub fn load_tasks<P: AsRef<Path> + ToString>(path: P) -> Result<Tasks, Box<dyn Error>> {
-    // Read TOML from the file
-    let data = fs::read_to_string(path)?;
+/// Loads tasks from a tasks file that has already been read as TOML
+pub fn from_table(path: &Path, table: toml::Table) -> Result<Tasks, InertiaError> {
+    let mut tasks: Tasks = toml::Value::Table(table)
+        .try_into()
+        .map_err(|err: toml::de::Error| InertiaError::toml(path, &err.to_string()))?;
 
-    // Load the tasks from TOML form
-    let tasks: Tasks = toml::from_str(&data)?;
+    // Tasks may not have been given an id yet, such as after a merge, or have untidy tags from older files
+    tasks.assign_ids();
+    tasks.normalise_tags();
 
     Ok(tasks)
 }
 
-pub fn ensure_repo(path: &str) -> Result<(), Box<dyn Error>> {
-    // Generate the path of the tasks file
-    let tasks_file_path = tasks_file_path();
+fn read_tasks(path: &Path) -> Result<(Tasks, u32), InertiaError> {
+    let (table, version) = read_table(path)?;
+    Ok((from_table(path, table)?, version))
+}
 
-    // Check if the path exists
-    if !Path::new(path).exists() {
-        output::warning(format!(
-            "tasks repository {path} does not exist. creating..."
-        ));
+/// Reads tasks from the contents of a tasks file, such as an older version from git
+pub fn parse_tasks(path: &Path, data: &str) -> Result<Tasks, InertiaError> {
+    let (table, _) = parse_table(path, data)?;
+    from_table(path, table)
+}
+
+/// Recovers the tasks from the backup of a file that can't be read, putting the backup back in its place
+fn recover_tasks(path: &Path, error: InertiaError) -> Result<(Tasks, u32), InertiaError> {
+    let backup = with_suffix(path, BACKUP_SUFFIX);
+    if !backup.exists() {
+        return Err(error);
+    }
+
+    // If the backup is broken too, the original problem is the one to report
+    let Ok(read) = read_tasks(&backup) else {
+        return Err(error);
+    };
+    let data =
+        fs::read_to_string(&backup).map_err(|err| InertiaError::io(&backup, &err.to_string()))?;
+    write_atomic(path, &data)?;
 
-        // Create the directory
-        fs::create_dir_all(path).unwrap();
-        // Generate a new empty tasks structure
-        let tasks = Tasks::new(path, TASKS_FILE);
+    output::warning(format!(
+        "recovered the tasks from {}, since {}",
+        backup.display(),
+        error
+    ));
+    Ok(read)
+}
+
+pub fn load_tasks(location: &Location) -> Result<Tasks, InertiaError> {
+    let path = location.tasks_file();
 
-        // Save the tasks
-        save_tasks(tasks_file_path, &tasks).unwrap();
+    // Fall back to the backup if the tasks can't be read, such as after a crash
+    let (mut tasks, version) = match read_tasks(&path) {
+        Ok(read) => read,
+        // Files from newer versions are fine, so they mustn't be replaced with the backup
+        Err(error @ InertiaError::Version(_)) => return Err(error),
+        Err(error) => recover_tasks(&path, error)?,
+    };
 
-        // Create the git repository
-        execute(path, String::from("init"))?;
-        execute(path, String::from("add --all"))?;
+    // Keep the file from before it was upgraded, the first time it's upgraded
+    if version < VERSION {
+        if version < 2 {
+            move_weights(&path)?;
+        }
 
-        // Success
-        output::success(format!("created tasks repo {path}"));
+        let original = with_suffix(&path, &format!(".v{}{}", version, BACKUP_SUFFIX));
+        if !original.exists() {
+            fs::copy(&path, &original)
+                .map_err(|err| InertiaError::io(&original, &err.to_string()))?;
+            output::info(format!(
+                "upgraded tasks from version {} to {}, the original is kept in {}",
+                version,
+                VERSION,
+                original.display()
+            ));
+        }
     }
 
+    // Remember where the tasks came from, since the repository can be moved
+    tasks.path = location.path.clone();
+    tasks.file = location.file.clone();
+    tasks.project = location.project;
+
+    // Write the upgrade back, so it's only done once and the uuids it gave out are kept
+    if version < VERSION {
+        // Repositories from older versions may not ignore backups or merge with inertia yet
+        if !location.project {
+            set_up_repo(location)?;
+        }
+        save_tasks(&path, &tasks)?;
+    }
+
+    Ok(tasks)
+}
+
+/// Moves the weights `next` scores tasks with from a tasks file from before version 2 to the config file
+fn move_weights(path: &Path) -> Result<(), InertiaError> {
+    // Weights already in the config file win, such as from another tasks file that was upgraded first
+    if config::get().next != Weights::default() {
+        return Ok(());
+    }
+
+    let data = fs::read_to_string(path).map_err(|err| InertiaError::io(path, &err.to_string()))?;
+    let table: toml::Table =
+        toml::from_str(&data).map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+    let Some(toml::Value::Table(weights)) = table.get("weights") else {
+        return Ok(());
+    };
+
+    // Only weights that were tuned are worth keeping, so the rest follow any new defaults
+    let defaults = toml::Table::try_from(Weights::default())
+        .map_err(|err| InertiaError::toml(path, &err.to_string()))?;
+    let mut moved = Vec::new();
+    for (name, value) in weights {
+        let key = format!("next.{}", name);
+        if defaults.get(name) != Some(value) && config::KEYS.iter().any(|(known, _)| *known == key)
+        {
+            config::set_value(&key, value.clone())?;
+            moved.push(key);
+        }
+    }
+
+    if !moved.is_empty() {
+        output::info(format!(
+            "moved {} from {} to the config file",
+            moved.join(", "),
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Keeps backups, temporary files and locks out of git, without changing anything that's committed
+fn ignore_backups(exclude: &Path, file: &Path) -> Result<(), InertiaError> {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let patterns = [
+        format!("{}{}", name, BACKUP_SUFFIX),
+        format!("{}{}", name, TEMP_SUFFIX),
+        format!("{}{}", name, LOCK_SUFFIX),
+        format!("{}{}.*.stale", name, LOCK_SUFFIX),
+        format!("{}.v*{}", name, BACKUP_SUFFIX),
+    ];
+
+    let io_error = |err: std::io::Error| InertiaError::io(exclude, &err.to_string());
+    let existing = if exclude.exists() {
+        fs::read_to_string(exclude).map_err(io_error)?
+    } else {
+        String::new()
+    };
+    let missing: Vec<&String> = patterns
+        .iter()
+        .filter(|pattern| !existing.lines().any(|line| line == pattern.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(dir) = exclude.parent() {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let mut exclude = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(exclude)
+        .map_err(io_error)?;
+    for pattern in missing {
+        writeln!(exclude, "{}", pattern).map_err(io_error)?;
+    }
     Ok(())
 }
 
-pub fn tasks_repo_string() -> String {
-    // Generate the path for the location of tasks
-    let home_dir = home_dir().unwrap();
-    let home_dir = home_dir.to_str().unwrap();
-    format!("{home_dir}/.local/share/inertia")
+/// Tells git to merge the tasks file with inertia, returning whether .gitattributes was changed
+fn register_merge_driver(
+    repo: &Repository,
+    path: &Path,
+    file: &Path,
+) -> Result<bool, InertiaError> {
+    // Git runs the driver through the shell, so the path to inertia needs quoting
+    let exe = env::current_exe().map_err(|err| InertiaError::io(path, &err.to_string()))?;
+    let driver = format!(
+        "'{}' merge-driver %O %A %B",
+        exe.to_string_lossy().replace('\'', "'\\''")
+    );
+    let key = format!("merge.{}.driver", MERGE_DRIVER);
+    if repo.config(&key).as_deref() != Some(driver.as_str()) {
+        repo.set_config(
+            &format!("merge.{}.name", MERGE_DRIVER),
+            "inertia task merge",
+        )?;
+        repo.set_config(&key, &driver)?;
+    }
+
+    // The attributes are committed, so every clone merges the same way
+    let attributes = path.join(ATTRIBUTES_FILE);
+    let line = format!(
+        "{} merge={}",
+        file.file_name().unwrap_or_default().to_string_lossy(),
+        MERGE_DRIVER
+    );
+    let io_error = |err: std::io::Error| InertiaError::io(&attributes, &err.to_string());
+    let existing = if attributes.exists() {
+        fs::read_to_string(&attributes).map_err(io_error)?
+    } else {
+        String::new()
+    };
+    if existing.lines().any(|existing| existing == line) {
+        return Ok(false);
+    }
+
+    let mut attributes = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&attributes)
+        .map_err(io_error)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(attributes).map_err(io_error)?;
+    }
+    writeln!(attributes, "{}", line).map_err(io_error)?;
+    Ok(true)
 }
 
-pub fn tasks_file_path() -> String {
-    format!("{}/{}", tasks_repo_string(), TASKS_FILE)
+/// Sets up a tasks repository so git ignores inertia's own files and merges tasks with it,
+/// committing .gitattributes if it changed
+pub fn set_up_repo(location: &Location) -> Result<(), InertiaError> {
+    let repo = Repository::open(&location.path)?;
+    ignore_backups(&repo.exclude_file(), &location.file)?;
+    if register_merge_driver(&repo, &location.path, &location.file)? {
+        repo.commit_file(ATTRIBUTES_FILE, "register merge driver")?;
+    }
+    Ok(())
 }
 
-pub fn sync(repo_path: &str, remote: String) -> Result<(), Box<dyn Error>> {
-    execute(
-        repo_path,
-        format!("pull --ff --no-rebase --no-edit --commit {remote}"),
-    )?;
-    execute(repo_path, format!("push {remote}"))?;
+/// Creates the tasks repository if it doesn't exist yet
+pub fn ensure_repo(path: &Path) -> Result<(), InertiaError> {
+    // Generate the path of the tasks file
+    let location = Location::repo(path.to_path_buf());
+    if path.exists() {
+        return Ok(());
+    }
+
+    // Create the directory, which the lock is kept in
+    fs::create_dir_all(path).map_err(|err| InertiaError::io(path, &err.to_string()))?;
+
+    // Another inertia may have been creating it at the same time, and got there first
+    let _lock = location.lock()?;
+    if path.join(".git").exists() {
+        return Ok(());
+    }
+    output::warning(format!(
+        "tasks repository {} does not exist. creating...",
+        path.display()
+    ));
+
+    // Generate a new empty tasks structure and save it
+    save_tasks(location.tasks_file(), &Tasks::new(&location))?;
+
+    // Create the git repository
+    let repo = Repository::init(path)?;
+    ignore_backups(&repo.exclude_file(), &location.file)?;
+    register_merge_driver(&repo, path, &location.file)?;
+    commit(path, "create tasks repository")?;
 
+    // Success
+    output::success(format!("created tasks repo {}", path.display()));
     Ok(())
 }
+
+/// Expands a leading ~ in a configured path to the home directory
+fn expand_home(path: &Path) -> Result<PathBuf, InertiaError> {
+    match path.strip_prefix("~") {
+        Ok(rest) => Ok(home_dir().ok_or_else(InertiaError::no_home)?.join(rest)),
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
+
+/// Finds the tasks repository, from --repo or $INERTIA_DIR, then the profile, the config file and $XDG_DATA_HOME
+pub fn tasks_repo_path(
+    repo: Option<PathBuf>,
+    profile: Option<&str>,
+) -> Result<PathBuf, InertiaError> {
+    // A repository given on the command line or in $INERTIA_DIR always wins
+    if let Some(repo) = repo {
+        return Ok(repo);
+    }
+
+    // Then the profile from --profile or the config file
+    let config = config::get();
+    if let Some(profile) = config.profile(profile)? {
+        return expand_home(&profile.path);
+    }
+
+    // Then the configured location of tasks
+    if let Some(path) = &config.repo.path {
+        return expand_home(path);
+    }
+
+    // Otherwise follow the XDG base directory spec, which ignores relative paths
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+    let data_home = match data_home {
+        Some(data_home) => data_home,
+        None => home_dir()
+            .ok_or_else(InertiaError::no_home)?
+            .join(".local")
+            .join("share"),
+    };
+    Ok(data_home.join("inertia"))
+}
+
+/// Finds the project a directory is in, from a .inertia directory or inertia.toml in it or one of its parents
+pub fn find_project(dir: &Path) -> Option<Location> {
+    dir.ancestors().find_map(|root| {
+        let file = if root.join(PROJECT_DIR).is_dir() {
+            Path::new(PROJECT_DIR).join(&config::get().repo.file)
+        } else if root.join(PROJECT_FILE).is_file() {
+            PathBuf::from(PROJECT_FILE)
+        } else {
+            return None;
+        };
+
+        Some(Location {
+            path: root.to_path_buf(),
+            file,
+            project: true,
+        })
+    })
+}
+
+/// Finds the tasks to use, preferring a project the working directory is in unless a repository was asked for
+pub fn locate(
+    repo: Option<PathBuf>,
+    profile: Option<&str>,
+    global: bool,
+) -> Result<Location, InertiaError> {
+    if repo.is_none() && profile.is_none() && !global {
+        let dir =
+            env::current_dir().map_err(|err| InertiaError::io(Path::new("."), &err.to_string()))?;
+        if let Some(project) = find_project(&dir) {
+            return Ok(project);
+        }
+    }
+
+    Ok(Location::repo(tasks_repo_path(repo, profile)?))
+}
+
+/// Creates a project's list of tasks, to be committed along with its code
+pub fn init_project(dir: &Path, single_file: bool) -> Result<Location, InertiaError> {
+    let location = Location {
+        path: dir.to_path_buf(),
+        file: if single_file {
+            PathBuf::from(PROJECT_FILE)
+        } else {
+            Path::new(PROJECT_DIR).join(&config::get().repo.file)
+        },
+        project: true,
+    };
+
+    let file = location.tasks_file();
+    if file.exists() {
+        return Err(InertiaError::project_exists(&file));
+    }
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).map_err(|err| InertiaError::io(parent, &err.to_string()))?;
+    }
+    save_tasks(&file, &Tasks::new(&location))?;
+
+    // Keep backups of the tasks out of the project's git repository
+    if let Some(repo) = Repository::discover(dir) {
+        ignore_backups(&repo.exclude_file(), &location.file)?;
+    }
+    stage_project(&location)?;
+
+    Ok(location)
+}
+
+/// Stages a project's tasks file, if the project uses git, so it's committed with the next change to the code
+pub fn stage_project(location: &Location) -> Result<(), InertiaError> {
+    let repo = match Repository::discover(&location.path) {
+        Some(repo) => repo,
+        None => return Ok(()),
+    };
+
+    // The project may be anywhere in the repository, so find the file from the top of it
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .map_err(|err| InertiaError::io(path, &err.to_string()))
+    };
+    let file = canonical(&location.tasks_file())?;
+    let workdir = canonical(repo.workdir().unwrap_or(&location.path))?;
+    match file.strip_prefix(&workdir) {
+        Ok(file) => repo.add(file),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Merges another branch into HEAD, merging the tasks task by task and leaving everything as
+/// it was if anything else can't be merged
+pub fn merge(path: &Path, file: &Path, branch: &str) -> Result<(), InertiaError> {
+    let tasks_file = path.join(file);
+    Repository::open(path)?.merge(branch, file, |base, ours, theirs| {
+        merge::merge_data(&tasks_file, base, ours, theirs).map(|(merged, _)| merged)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V0: &str = r#"
+path = "/home/user/.local/share/inertia"
+file = "tasks.toml"
+
+[[tasks]]
+title = "first"
+status = "Inbox"
+
+[[tasks]]
+title = "second"
+status = "Complete"
+"#;
+
+    fn parse(data: &str) -> Tasks {
+        parse_tasks(Path::new("tasks.toml"), data).unwrap()
+    }
+
+    #[test]
+    fn upgrade_gives_old_tasks_ids() {
+        let (table, version) = parse_table(Path::new("tasks.toml"), V0).unwrap();
+        assert_eq!(version, 0);
+        assert!(!table.contains_key("path"));
+        assert!(!table.contains_key("file"));
+
+        let tasks = from_table(Path::new("tasks.toml"), table).unwrap();
+        assert_eq!(tasks.version, VERSION);
+        assert_eq!(tasks.next_id, 3);
+        let ids: Vec<usize> = tasks.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_ne!(tasks.tasks[0].uuid, tasks.tasks[1].uuid);
+    }
+
+    #[test]
+    fn upgrade_gives_the_same_uuids_every_time() {
+        let uuids =
+            |tasks: Tasks| -> Vec<Uuid> { tasks.tasks.iter().map(|task| task.uuid).collect() };
+        assert_eq!(uuids(parse(V0)), uuids(parse(V0)));
+    }
+
+    #[test]
+    fn upgrade_keeps_existing_uuids_and_ids() {
+        let data = r#"
+next_id = 8
+
+[[tasks]]
+uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8"
+id = 7
+title = "kept"
+status = "Inbox"
+
+[[tasks]]
+title = "new"
+status = "Inbox"
+"#;
+        let tasks = parse(data);
+        assert_eq!(
+            tasks.tasks[0].uuid.to_string(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(tasks.tasks[0].id, 7);
+        assert_eq!(tasks.tasks[1].id, 8);
+    }
+
+    #[test]
+    fn upgrade_leaves_weights_to_the_config() {
+        let data = "version = 1\ntasks = []\n\n[weights]\nactive = 20.0\n";
+        let (table, version) = parse_table(Path::new("tasks.toml"), data).unwrap();
+        assert_eq!(version, 1);
+        assert!(!table.contains_key("weights"));
+        assert_eq!(table.get("version"), Some(&toml::Value::from(VERSION)));
+    }
+
+    #[test]
+    fn newer_files_are_refused() {
+        let data = format!("version = {}\ntasks = []\n", VERSION + 1);
+        assert!(matches!(
+            parse_table(Path::new("tasks.toml"), &data),
+            Err(InertiaError::Version(_))
+        ));
+    }
+}
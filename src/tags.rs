@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::InertiaError;
+use crate::tasks::{Task, Tasks};
+
+/// Tidies a tag into its canonical form, such as " Work / Client A" into "work/client-a"
+pub fn normalise(tag: &str) -> Option<String> {
+    let parts: Vec<String> = tag
+        .split('/')
+        .map(|part| {
+            part.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join("-")
+                .to_lowercase()
+        })
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+/// Splits tags separated by commas, normalising them and removing duplicates
+pub fn parse(tags: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for tag in tags.split(',').filter_map(normalise) {
+        if !parsed.contains(&tag) {
+            parsed.push(tag);
+        }
+    }
+    parsed
+}
+
+/// Checks if a tag is the same as, or nested under, another tag
+pub fn is_within(tag: &str, parent: &str) -> bool {
+    tag == parent || tag.starts_with(&format!("{}/", parent))
+}
+
+/// Returns a tag and every tag it's nested under, such as "a/b/c", "a/b" and "a"
+fn ancestors(tag: &str) -> Vec<&str> {
+    let mut ancestors = vec![tag];
+    let mut rest = tag;
+    while let Some((parent, _)) = rest.rsplit_once('/') {
+        ancestors.push(parent);
+        rest = parent;
+    }
+    ancestors
+}
+
+/// Moves a tag (and anything nested under it) from one name to another
+fn replace(tag: &str, from: &str, to: &str) -> Option<String> {
+    if is_within(tag, from) {
+        Some(format!("{}{}", to, &tag[from.len()..]))
+    } else {
+        None
+    }
+}
+
+fn rewrite(
+    tags: &mut Option<Vec<String>>,
+    change: impl Fn(&str) -> Option<Option<String>>,
+) -> bool {
+    let Some(old) = tags else {
+        return false;
+    };
+
+    // Apply the change to each tag, keeping the order and dropping duplicates
+    let mut changed = false;
+    let mut new: Vec<String> = Vec::new();
+    for tag in old.iter() {
+        let tag = match change(tag) {
+            Some(replacement) => {
+                changed = true;
+                replacement
+            }
+            None => Some(tag.clone()),
+        };
+        if let Some(tag) = tag {
+            if !new.contains(&tag) {
+                new.push(tag);
+            }
+        }
+    }
+
+    *tags = if new.is_empty() { None } else { Some(new) };
+    changed
+}
+
+impl Task {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().flatten().any(|own| is_within(own, tag))
+    }
+
+    pub fn add_tag(&mut self, tag: String) {
+        let tags = self.tags.get_or_insert_with(Vec::new);
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        rewrite(&mut self.tags, |own| (own == tag).then_some(None));
+    }
+}
+
+impl Tasks {
+    /// Tidies up tags from older versions which may have stray spaces or capitals
+    pub fn normalise_tags(&mut self) {
+        for task in self.tasks.iter_mut() {
+            rewrite(&mut task.tags, |tag| {
+                let normalised = normalise(tag);
+                (normalised.as_deref() != Some(tag)).then_some(normalised)
+            });
+        }
+    }
+
+    /// Counts the tasks under each tag, including tags that only have nested tags
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for task in &self.tasks {
+            // Each task only counts once towards a parent, however many nested tags it has
+            let mut seen: Vec<&str> = Vec::new();
+            for tag in task.tags.iter().flatten() {
+                for tag in ancestors(tag) {
+                    if !seen.contains(&tag) {
+                        seen.push(tag);
+                        *counts.entry(tag.to_string()).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// Applies a change to the tags of every task and area, and the scores for tags, returning how many tasks changed
+    fn rewrite_tags(
+        &mut self,
+        weights: &mut HashMap<String, f64>,
+        change: impl Fn(&str) -> Option<Option<String>>,
+    ) -> usize {
+        for area in self.areas.iter_mut() {
+            rewrite(&mut area.tags, &change);
+        }
+
+        // Keep the scoring weights for tags pointing at the right tags
+        for (tag, weight) in weights.drain().collect::<Vec<(String, f64)>>() {
+            match change(&tag) {
+                Some(Some(tag)) => weights.insert(tag, weight),
+                Some(None) => None,
+                None => weights.insert(tag, weight),
+            };
+        }
+
+        let mut changed = 0;
+        for task in self.tasks.iter_mut() {
+            if rewrite(&mut task.tags, &change) {
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    fn ensure_tag(&self, tag: &str) -> Result<(), InertiaError> {
+        if self.tag_counts().contains_key(tag) {
+            Ok(())
+        } else {
+            Err(InertiaError::no_tag(tag))
+        }
+    }
+
+    /// Renames a tag and the tags nested under it
+    pub fn rename_tag(
+        &mut self,
+        from: &str,
+        to: &str,
+        weights: &mut HashMap<String, f64>,
+    ) -> Result<usize, InertiaError> {
+        self.ensure_tag(from)?;
+        if self.tag_counts().contains_key(to) {
+            return Err(InertiaError::tag_exists(to));
+        }
+
+        Ok(self.rewrite_tags(weights, |tag| replace(tag, from, to).map(Some)))
+    }
+
+    /// Merges tags, and the tags nested under them, into another tag
+    pub fn merge_tags(
+        &mut self,
+        from: &[String],
+        into: &str,
+        weights: &mut HashMap<String, f64>,
+    ) -> Result<usize, InertiaError> {
+        for tag in from {
+            self.ensure_tag(tag)?;
+        }
+
+        Ok(self.rewrite_tags(weights, |tag| {
+            from.iter()
+                .find_map(|from| replace(tag, from, into))
+                .map(Some)
+        }))
+    }
+
+    /// Removes a tag, and the tags nested under it, from every task
+    pub fn delete_tag(
+        &mut self,
+        tag: &str,
+        weights: &mut HashMap<String, f64>,
+    ) -> Result<usize, InertiaError> {
+        self.ensure_tag(tag)?;
+
+        Ok(self.rewrite_tags(weights, |own| is_within(own, tag).then_some(None)))
+    }
+}
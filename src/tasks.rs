@@ -1,19 +1,18 @@
 use chrono::{Local, NaiveDateTime};
 use colored::{ColoredString, Colorize};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
 
-#[derive(Debug)]
-pub struct TasksError(String);
+use crate::areas::Area;
+use crate::config;
+use crate::error::InertiaError;
+use crate::links::Link;
+use crate::recurrence::Recurrence;
+use crate::repo::{self, Location};
 
-impl TasksError {
-    pub fn no_task(id: usize) -> Self {
-        Self(format!("couldn't find task with id {}", id))
-    }
-
-    pub fn no_tasks() -> Self {
-        Self(String::from("no tasks available"))
-    }
-}
+/// Uuid prefixes made only of digits have to be this long, so a mistyped id doesn't pick out a random task
+pub const MIN_NUMERIC_PREFIX: usize = 8;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Status {
@@ -24,6 +23,16 @@ pub enum Status {
 }
 
 impl Status {
+    /// The name of the status, as used in filters and JSON
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Inbox => "inbox",
+            Status::Pending => "pending",
+            Status::Active => "active",
+            Status::Complete => "complete",
+        }
+    }
+
     pub fn as_colored_string(&self) -> ColoredString {
         match self {
             Status::Inbox => "📮 Inbox".blue(),
@@ -36,13 +45,21 @@ impl Status {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
-    pub title: String,                   // The required title of the task
-    pub status: Status,                  // Current status of the task
-    pub notes: Option<String>,           // Any notes to explain the task
-    pub tags: Option<Vec<String>>,       // Tasks can be tagged for organisation
-    pub when: Option<NaiveDateTime>,     // The date you want to do the task
+    pub uuid: Uuid, // Unique identifier that never changes
+    #[serde(default)]
+    pub id: usize, // Short number shown to the user, 0 until assigned
+    pub title: String, // The required title of the task
+    pub status: Status, // Current status of the task
+    pub notes: Option<String>, // Any notes to explain the task
+    pub area: Option<String>, // The name of the area the task belongs to
+    pub tags: Option<Vec<String>>, // Tasks can be tagged for organisation
+    pub when: Option<NaiveDateTime>, // The date you want to do the task
     pub deadline: Option<NaiveDateTime>, // The latest date the task should be done
     pub reminder: Option<NaiveDateTime>, // The datetime a reminder will alert you
+    pub recurrence: Option<Recurrence>, // How often the task repeats once completed
+    pub created: Option<NaiveDateTime>, // When the task was created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>, // Links from this task to other tasks
 }
 
 impl Task {
@@ -61,13 +78,19 @@ impl Task {
         };
 
         Self {
+            uuid: Uuid::new_v4(),
+            id: 0,
             title,
             status,
             notes,
+            area: None,
             tags,
             when,
             deadline,
             reminder,
+            recurrence: None,
+            created: Some(Local::now().naive_local()),
+            links: Vec::new(),
         }
     }
 
@@ -89,7 +112,7 @@ impl Task {
 
         if let Some(_when) = self.when {
             if self.is_inbox() {
-                self.status = Status::Pending;
+                self.pend();
             }
         }
     }
@@ -101,8 +124,14 @@ impl Task {
         self.when = None;
     }
 
-    pub fn complete(&mut self) {
+    /// Completes the task, returning its next occurrence if it repeats, or None if it was already complete
+    pub fn complete(&mut self) -> Option<Task> {
+        if self.is_complete() {
+            return None;
+        }
         self.status = Status::Complete;
+        let recurrence = self.recurrence.as_ref()?;
+        self.next_occurrence(recurrence, Local::now().naive_local())
     }
 
     pub fn start(&mut self) {
@@ -122,6 +151,7 @@ impl Task {
     }
 }
 
+#[allow(dead_code)]
 impl Task {
     pub fn is_complete(&self) -> bool {
         self.status == Status::Complete
@@ -144,7 +174,7 @@ impl Task {
     fn date_colored_string(&self, date: &Option<NaiveDateTime>) -> ColoredString {
         if let Some(date) = date {
             let date = date.date();
-            let date_string = format!("{}", date.format("%Y-%m-%d"));
+            let date_string = format!("{}", date.format(&config::get().display.date_format));
             let now = Local::now().date_naive();
 
             if date <= now {
@@ -175,6 +205,25 @@ impl Task {
         self.date_colored_string(&self.reminder)
     }
 
+    pub fn created_string(&self) -> ColoredString {
+        if let Some(created) = self.created {
+            created
+                .format(&config::get().display.date_format)
+                .to_string()
+                .white()
+        } else {
+            "N/A".bright_black()
+        }
+    }
+
+    pub fn id_string(&self) -> String {
+        self.id.to_string()
+    }
+
+    pub fn uuid_string(&self) -> String {
+        self.uuid.hyphenated().to_string()
+    }
+
     pub fn title_string(&self) -> ColoredString {
         self.title.white()
     }
@@ -191,6 +240,22 @@ impl Task {
         }
     }
 
+    pub fn recurrence_string(&self) -> ColoredString {
+        if let Some(recurrence) = &self.recurrence {
+            recurrence.to_string().white()
+        } else {
+            "N/A".bright_black()
+        }
+    }
+
+    pub fn area_string(&self) -> ColoredString {
+        if let Some(area) = &self.area {
+            area.white()
+        } else {
+            "N/A".bright_black()
+        }
+    }
+
     pub fn notes_string(&self) -> ColoredString {
         if let Some(notes) = &self.notes {
             notes.white()
@@ -202,19 +267,42 @@ impl Task {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Tasks {
-    pub path: String,             // Path to the tasks repository
-    pub file: String,             // Path to the tasks file in the repository
+    #[serde(default)]
+    pub version: u32, // The version of inertia the file was written for, so it can be upgraded
+    #[serde(skip)]
+    pub path: PathBuf, // Path to the tasks repository, found when the tasks are loaded
+    #[serde(skip)]
+    pub file: PathBuf, // Path of the tasks file in the repository
+    #[serde(skip)]
+    pub project: bool, // Whether the tasks belong to a project, and are committed along with its code
+    #[serde(default)]
+    pub next_id: usize, // The id the next new task will be given
+    #[serde(default)]
+    pub areas: Vec<Area>, // Areas that tasks can be grouped into
     pub tasks: Vec<Task>, // All the tasks in one vector
 }
 
 impl Tasks {
-    pub fn new(repo_path: &str, tasks_file: &str) -> Self {
+    pub fn new(location: &Location) -> Self {
         Self {
-            path: String::from(repo_path),
-            file: String::from(tasks_file),
+            version: repo::VERSION,
+            path: location.path.clone(),
+            file: location.file.clone(),
+            project: location.project,
+            next_id: 1,
+            areas: Vec::new(),
             tasks: Vec::new(),
         }
     }
+
+    /// Where the tasks were loaded from
+    pub fn location(&self) -> Location {
+        Location {
+            path: self.path.clone(),
+            file: self.file.clone(),
+            project: self.project,
+        }
+    }
 }
 
 impl Tasks {
@@ -223,48 +311,79 @@ impl Tasks {
         self.len() == 0
     }
 
-    /// Checks if a task exists from an id
-    pub fn exists(&self, id: usize) -> bool {
-        id < self.len()
+    /// Finds the index of a task from its id or (a prefix of) its uuid
+    pub fn position(&self, id: &str) -> Result<usize, InertiaError> {
+        if self.is_empty() {
+            return Err(InertiaError::no_tasks());
+        }
+
+        // Short ids are plain numbers, though uuids can start with a long run of digits too
+        if let Ok(number) = id.parse::<usize>() {
+            let index = self.tasks.iter().position(|task| task.id == number);
+            match index {
+                Some(index) => return Ok(index),
+                None if id.len() < MIN_NUMERIC_PREFIX => return Err(InertiaError::no_task(id)),
+                None => {}
+            }
+        }
+
+        // Otherwise match against the start of the uuid, like git does with hashes
+        let prefix = id.to_lowercase();
+        let mut matches = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.uuid_string().starts_with(&prefix))
+            .map(|(index, _)| index);
+
+        match (matches.next(), matches.next()) {
+            (Some(index), None) => Ok(index),
+            (Some(_), Some(_)) => Err(InertiaError::ambiguous_task(id)),
+            _ => Err(InertiaError::no_task(id)),
+        }
     }
 
-    /// Returns a task from an id
-    pub fn task(&mut self, id: usize) -> Result<&mut Task, TasksError> {
-        if self.is_empty() {
-            Err(TasksError::no_tasks())
-        } else if self.exists(id) {
-            Ok(&mut self.tasks[id])
-        } else {
-            Err(TasksError::no_task(id))
+    /// Gives any tasks without an id (such as conflict tasks from a merge) a new one
+    pub fn assign_ids(&mut self) {
+        for index in 0..self.len() {
+            if self.tasks[index].id == 0 {
+                self.tasks[index].id = self.allocate_id();
+            }
         }
     }
+
+    /// Reserves the next unused id
+    fn allocate_id(&mut self) -> usize {
+        let highest = self.tasks.iter().map(|task| task.id).max().unwrap_or(0);
+        let id = self.next_id.max(highest + 1);
+        self.next_id = id + 1;
+        id
+    }
 }
 
 impl Tasks {
-    pub fn push(&mut self, task: Task) {
-        if self.is_empty() {
-            self.tasks = vec![task];
-        } else {
-            self.tasks.push(task);
-        };
+    /// Adds a task, giving it the next id
+    pub fn push(&mut self, mut task: Task) -> &Task {
+        task.id = self.allocate_id();
+        self.tasks.push(task);
+        self.tasks.last().unwrap()
     }
 
-    pub fn remove(&mut self, id: usize) -> Result<(), TasksError> {
-        if self.exists(id) {
-            self.tasks.remove(id);
-            Ok(())
-        } else {
-            Err(TasksError::no_task(id))
-        }
+    /// Removes a task, returning it
+    pub fn remove(&mut self, id: &str) -> Result<Task, InertiaError> {
+        let index = self.position(id)?;
+        let task = self.tasks.remove(index);
+        self.forget_links(task.uuid);
+        Ok(task)
     }
 
     pub fn len(&self) -> usize {
         self.tasks.len()
     }
 
-    pub fn clear(&mut self) -> Result<(), TasksError> {
+    pub fn clear(&mut self) -> Result<(), InertiaError> {
         if self.is_empty() {
-            Err(TasksError::no_tasks())
+            Err(InertiaError::no_tasks())
         } else {
             self.tasks = Vec::new();
             Ok(())